@@ -0,0 +1,252 @@
+//! Options making the game easier to see and follow.
+//!
+//! This module provides:
+//! - The `Palette` everything in the arena is drawn in: the classic colors
+//!   from the tuning, a colorblind-safe one and a high-contrast one
+//! - The `ArenaColors` system parameter, giving the colors of the current
+//!   palette to the systems drawing with them
+//! - The `AccessibilitySettings` resource with the palette, how fast the ball
+//!   is served, the size of the scoreboard's text and whether motion is reduced
+//!
+//! Every option applies as soon as it changes. The ball's speed changes the
+//! simulation, so it is copied into the `MatchRules` and applies from the next
+//! serve; the others only change what is drawn. Reduced motion keeps the
+//! camera still and the paddles in shape, and leaves particles where they
+//! burst instead of flying out.
+
+use bevy::{
+    color::{Alpha, Color},
+    ecs::{
+        change_detection::DetectChanges,
+        resource::Resource,
+        system::{Res, SystemParam},
+    },
+    render::camera::ClearColor,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{powerup::PowerUpKind, tuning::PaddleTuning};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Palette {
+    /// The colors from the tuning, on a dark arena with black gutters.
+    #[default]
+    Classic,
+    /// Orange against sky blue, which stay apart under every common form of
+    /// color blindness.
+    Colorblind,
+    /// Bright, saturated colors on a black arena.
+    HighContrast,
+}
+
+/// What everything in the arena is drawn in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Colors {
+    pub player: Color,
+    /// Every paddle not played by the player.
+    pub opponent: Color,
+    pub ball: Color,
+    /// Particles bursting where a ball touches something.
+    pub particle: Color,
+    /// The see-through ghosts every ball leaves behind.
+    pub trail: Color,
+    pub gutter: Color,
+    pub arena: Color,
+    pub obstacle: Color,
+    /// Bricks with one, two, and three or more hits left.
+    pub bricks: [Color; 3],
+    /// Power-ups in the order `PowerUpKind` declares them.
+    pub power_ups: [Color; 5],
+}
+
+impl Colors {
+    pub fn paddle(&self, is_player: bool) -> Color {
+        if is_player {
+            self.player
+        } else {
+            self.opponent
+        }
+    }
+
+    /// Bricks are colored by how many hits they have left.
+    pub fn brick(&self, hit_points: u32) -> Color {
+        self.bricks[(hit_points.max(1) as usize - 1).min(self.bricks.len() - 1)]
+    }
+
+    pub fn power_up(&self, kind: PowerUpKind) -> Color {
+        self.power_ups[kind as usize]
+    }
+}
+
+impl Palette {
+    pub const ALL: [Self; 3] = [Self::Classic, Self::Colorblind, Self::HighContrast];
+
+    /// The palette after this one, wrapping around.
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&palette| palette == self);
+        Self::ALL[index.map_or(0, |index| (index + 1) % Self::ALL.len())]
+    }
+
+    pub fn colors(self, tuning: &PaddleTuning) -> Colors {
+        match self {
+            Self::Classic => Colors {
+                player: tuning.paddle_one_color,
+                opponent: tuning.paddle_two_color,
+                ball: tuning.ball_color,
+                particle: Color::srgb(1.0, 0.9, 0.6),
+                trail: tuning.ball_color.with_alpha(0.35),
+                gutter: Color::BLACK,
+                // The color the whole window used to be cleared to
+                arena: ClearColor::default().0,
+                obstacle: Color::srgb(0.5, 0.5, 0.5),
+                bricks: [
+                    Color::srgb(0.3, 0.8, 0.4),
+                    Color::srgb(0.9, 0.8, 0.2),
+                    Color::srgb(0.9, 0.4, 0.2),
+                ],
+                power_ups: [
+                    Color::srgb(0.2, 0.9, 0.3),
+                    Color::srgb(0.9, 0.2, 0.2),
+                    Color::srgb(0.95, 0.85, 0.2),
+                    Color::srgb(1.0, 0.5, 0.1),
+                    Color::srgb(0.7, 0.3, 0.9),
+                ],
+            },
+            Self::Colorblind => Colors {
+                player: Color::srgb(0.9, 0.6, 0.0),
+                opponent: Color::srgb(0.34, 0.71, 0.91),
+                ball: Color::WHITE,
+                particle: Color::srgb(0.94, 0.89, 0.26),
+                trail: Color::WHITE.with_alpha(0.35),
+                gutter: Color::srgb(0.45, 0.45, 0.45),
+                arena: Color::srgb(0.1, 0.1, 0.1),
+                obstacle: Color::srgb(0.6, 0.6, 0.6),
+                // Yellow, bluish green and vermillion, darker with every hit
+                // left so they differ in lightness too
+                bricks: [
+                    Color::srgb(0.94, 0.89, 0.26),
+                    Color::srgb(0.0, 0.62, 0.45),
+                    Color::srgb(0.84, 0.37, 0.0),
+                ],
+                power_ups: [
+                    Color::srgb(0.0, 0.62, 0.45),
+                    Color::srgb(0.84, 0.37, 0.0),
+                    Color::srgb(0.94, 0.89, 0.26),
+                    Color::srgb(0.0, 0.45, 0.7),
+                    Color::srgb(0.8, 0.6, 0.7),
+                ],
+            },
+            Self::HighContrast => Colors {
+                player: Color::WHITE,
+                opponent: Color::srgb(0.0, 1.0, 1.0),
+                ball: Color::srgb(1.0, 1.0, 0.0),
+                particle: Color::WHITE,
+                trail: Color::srgb(1.0, 1.0, 0.0).with_alpha(0.5),
+                gutter: Color::srgb(0.6, 0.6, 0.6),
+                arena: Color::BLACK,
+                obstacle: Color::srgb(0.85, 0.85, 0.85),
+                bricks: [
+                    Color::srgb(0.0, 1.0, 0.0),
+                    Color::srgb(1.0, 0.5, 0.0),
+                    Color::srgb(1.0, 0.0, 1.0),
+                ],
+                power_ups: [
+                    Color::srgb(0.0, 1.0, 0.0),
+                    Color::srgb(1.0, 0.0, 0.0),
+                    Color::srgb(1.0, 0.0, 1.0),
+                    Color::srgb(1.0, 0.5, 0.0),
+                    Color::srgb(0.5, 0.5, 1.0),
+                ],
+            },
+        }
+    }
+}
+
+/// The colors of the current palette, for systems drawing the arena.
+#[derive(SystemParam)]
+pub struct ArenaColors<'w> {
+    tuning: Res<'w, PaddleTuning>,
+    accessibility: Res<'w, AccessibilitySettings>,
+}
+
+impl ArenaColors<'_> {
+    pub fn get(&self) -> Colors {
+        self.accessibility.palette.colors(&self.tuning)
+    }
+
+    /// Whether the colors may have changed since the system last ran, as they
+    /// do when the tuning or the palette changes.
+    pub fn is_changed(&self) -> bool {
+        self.tuning.is_changed() || self.accessibility.is_changed()
+    }
+}
+
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccessibilitySettings {
+    pub palette: Palette,
+    /// Fraction of the tuned speed the ball is served at, below 1 to slow the
+    /// game down.
+    pub ball_speed: f32,
+    /// Scale of the scoreboard's text.
+    pub text_scale: f32,
+    pub reduced_motion: bool,
+}
+
+impl Default for AccessibilitySettings {
+    fn default() -> Self {
+        Self {
+            palette: Palette::default(),
+            ball_speed: 1.,
+            text_scale: 1.,
+            reduced_motion: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn palettes_cycle_through_every_one() {
+        let mut palette = Palette::Classic;
+        for expected in [Palette::Colorblind, Palette::HighContrast, Palette::Classic] {
+            palette = palette.next();
+            assert_eq!(palette, expected);
+        }
+    }
+
+    #[test]
+    fn classic_palette_follows_the_tuning() {
+        let tuning = PaddleTuning {
+            ball_color: Color::srgb(0., 1., 0.),
+            ..Default::default()
+        };
+
+        let colors = Palette::Classic.colors(&tuning);
+        assert_eq!(colors.ball, tuning.ball_color);
+        assert_eq!(colors.player, tuning.paddle_one_color);
+        assert_eq!(colors.opponent, tuning.paddle_two_color);
+        // The trail is a see-through ghost of the ball
+        assert_eq!(colors.trail.with_alpha(1.), tuning.ball_color);
+    }
+
+    #[test]
+    fn bricks_past_the_last_color_share_it() {
+        let colors = Palette::Classic.colors(&PaddleTuning::default());
+
+        assert_eq!(colors.brick(0), colors.bricks[0]);
+        assert_eq!(colors.brick(2), colors.bricks[1]);
+        assert_eq!(colors.brick(7), colors.bricks[2]);
+    }
+
+    #[test]
+    fn missing_options_keep_their_defaults() {
+        let settings: AccessibilitySettings = ron::from_str("(reduced_motion: true)").unwrap();
+
+        assert!(settings.reduced_motion);
+        assert_eq!(settings.ball_speed, 1.);
+        assert_eq!(settings.palette, Palette::Classic);
+    }
+}
@@ -0,0 +1,309 @@
+//! AI module steering computer controlled paddles.
+//!
+//! A paddle with an `OpponentAi` component predicts where the ball will cross
+//! its face, bouncing off the gutters on the way, and moves there. To keep it
+//! beatable each `Difficulty` preset:
+//! - reacts only after a delay once the ball starts heading towards it
+//! - aims with a random error, picked once per approach
+//! - moves at a fraction of the paddle speed, easing off near its target so it
+//!   does not jitter around it
+//!
+//! While the ball is heading away the AI drifts back to the middle of its lane.
+//! With several balls in play it goes after whichever will reach it first.
+//!
+//! The AI plays a paddle on any edge: it looks at the ball as if its paddle
+//! stood upright, with the lane running along its edge.
+
+use bevy::{
+    ecs::{
+        change_detection::DetectChanges,
+        component::Component,
+        entity::Entity,
+        query::{With, Without},
+        resource::Resource,
+        system::{Commands, Query, Res, ResMut},
+    },
+    math::Vec2,
+    time::{Time, Timer, TimerMode},
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::{Ball, Edge, Gutter, Opponent, Paddle, Position, Shape, Speed, Velocity},
+    game::{GameMode, GameRng},
+};
+
+/// Distance from its target at which the AI starts easing off.
+const SLOWDOWN_DISTANCE: f32 = 40.;
+
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Medium,
+    Hard,
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct OpponentAi {
+    /// Seconds between the ball turning towards the paddle and the AI reacting.
+    pub reaction_delay: f32,
+    /// Largest distance, either way, the AI may be off from the real intercept.
+    pub aim_error: f32,
+    /// Fraction of the paddle speed the AI moves at.
+    pub max_speed: f32,
+    /// Distance from its target within which the AI stops moving.
+    pub dead_zone: f32,
+    reaction: Timer,
+    error: f32,
+    approaching: bool,
+}
+
+impl OpponentAi {
+    pub fn new(difficulty: Difficulty) -> Self {
+        let (reaction_delay, aim_error, max_speed) = match difficulty {
+            Difficulty::Easy => (0.35, 45., 0.6),
+            Difficulty::Medium => (0.2, 22., 0.8),
+            Difficulty::Hard => (0.08, 6., 1.),
+        };
+
+        Self {
+            reaction_delay,
+            aim_error,
+            max_speed,
+            dead_zone: 4.,
+            reaction: Timer::from_seconds(reaction_delay, TimerMode::Once),
+            error: 0.,
+            approaching: false,
+        }
+    }
+
+    /// Forgets everything about the current rally.
+    fn reset(&mut self) {
+        self.reaction.reset();
+        self.error = 0.;
+        self.approaching = false;
+    }
+}
+
+/// Predicts the height at which a ball at `ball` travelling along `direction`
+/// reaches `target_x`, reflecting off horizontal walls that keep its center
+/// between `bottom` and `top`, which may be infinitely far away. Returns
+/// `None` when the ball is heading away.
+pub fn predict_intercept(
+    ball: Vec2,
+    direction: Vec2,
+    target_x: f32,
+    bottom: f32,
+    top: f32,
+) -> Option<f32> {
+    let distance = target_x - ball.x;
+    if direction.x == 0. || distance.signum() != direction.x.signum() {
+        return None;
+    }
+
+    let unfolded = ball.y + direction.y * distance / direction.x;
+
+    // Bouncing between two walls is the same as travelling in a straight line
+    // through mirrored copies of the lane, so fold the straight line back in.
+    let span = top - bottom;
+    if span.is_infinite() {
+        return Some(unfolded);
+    }
+    if span <= 0. {
+        return Some((top + bottom) / 2.);
+    }
+    let offset = (unfolded - bottom).rem_euclid(2. * span);
+    if offset <= span {
+        Some(bottom + offset)
+    } else {
+        Some(top - (offset - span))
+    }
+}
+
+/// Seconds until a ball at `ball` moving at `velocity` reaches `target_x`, or
+/// `None` when it is heading away or not moving.
+pub fn time_to_reach(ball: Vec2, velocity: Vec2, target_x: f32) -> Option<f32> {
+    let time = (target_x - ball.x) / velocity.x;
+    (time.is_finite() && time >= 0.).then_some(time)
+}
+
+/// Whether the AI is playing in the current game mode.
+pub fn enabled(mode: Res<GameMode>) -> bool {
+    mode.has_ai()
+}
+
+/// Gives the opponent an AI for the current difficulty whenever it changes.
+pub fn apply_difficulty(
+    mut commands: Commands,
+    difficulty: Res<Difficulty>,
+    opponent: Query<Entity, With<Opponent>>,
+) {
+    if !difficulty.is_changed() {
+        return;
+    }
+
+    for entity in &opponent {
+        commands.entity(entity).insert(OpponentAi::new(*difficulty));
+    }
+}
+
+/// Clears every AI's memory of the last match before a new one.
+pub fn reset(mut ais: Query<&mut OpponentAi>) {
+    for mut ai in &mut ais {
+        ai.reset();
+    }
+}
+
+/// A paddle with the AI steering it.
+type SteeredPaddle<'a> = (
+    &'a Position,
+    &'a Shape,
+    &'a Edge,
+    &'a mut Velocity,
+    &'a mut OpponentAi,
+);
+
+/// A ball with what the AI reads to predict where it goes.
+type TrackedBall<'a> = (&'a Position, &'a Velocity, &'a Speed, &'a Shape);
+
+pub fn steer(
+    mut paddles: Query<SteeredPaddle, With<Paddle>>,
+    balls: Query<TrackedBall, (With<Ball>, Without<Paddle>)>,
+    gutters: Query<(&Position, &Shape, &Gutter), Without<Paddle>>,
+    time: Res<Time>,
+    mut rng: ResMut<GameRng>,
+) {
+    // Inner faces of the gutters, which keep a ball's center a radius away.
+    // Only upright paddles have gutters at the ends of their lane.
+    let mut bottom = f32::NEG_INFINITY;
+    let mut top = f32::INFINITY;
+    for (position, shape, gutter) in &gutters {
+        let half_height = shape.size.y / 2.;
+        match gutter.0 {
+            Edge::Top => top = top.min(position.coords.y - half_height),
+            Edge::Bottom => bottom = bottom.max(position.coords.y + half_height),
+            Edge::Right | Edge::Left => {}
+        }
+    }
+
+    for (position, shape, &edge, mut velocity, mut ai) in &mut paddles {
+        // Turns the arena so the paddle stands upright, its lane along y
+        let axis = edge.axis();
+        let across = edge.outward().abs();
+        let upright = |v: Vec2| Vec2::new(v.dot(across), v.dot(axis));
+        let (bottom, top) = if axis == Vec2::Y {
+            (bottom, top)
+        } else {
+            (f32::NEG_INFINITY, f32::INFINITY)
+        };
+        let paddle = upright(position.coords);
+
+        // With more than one ball in play, the one to stop is whichever
+        // reaches the paddle first
+        let intercept = balls
+            .iter()
+            .filter_map(|(ball_position, ball_velocity, speed, ball_shape)| {
+                let radius = ball_shape.size.x;
+                let ball = upright(ball_position.coords);
+                let direction = upright(ball_velocity.direction);
+                // The face the ball is hit with is the one pointing at the middle
+                let face_x = paddle.x - paddle.x.signum() * (upright(shape.size).x / 2. + radius);
+                let arrival = time_to_reach(ball, direction * speed.0, face_x)?;
+                let intercept =
+                    predict_intercept(ball, direction, face_x, bottom + radius, top - radius)?;
+                Some((arrival, intercept))
+            })
+            .min_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, intercept)| intercept);
+
+        let target = match intercept {
+            Some(intercept) => {
+                if !ai.approaching {
+                    ai.approaching = true;
+                    ai.reaction.reset();
+                    ai.error = if ai.aim_error > 0. {
+                        rng.gen_range(-ai.aim_error..=ai.aim_error)
+                    } else {
+                        0.
+                    };
+                }
+
+                if !ai.reaction.tick(time.delta()).finished() {
+                    velocity.direction = Vec2::ZERO;
+                    continue;
+                }
+
+                intercept + ai.error
+            }
+            None => {
+                ai.approaching = false;
+                0.
+            }
+        };
+
+        let distance = target - paddle.y;
+        velocity.direction = axis
+            * if distance.abs() <= ai.dead_zone {
+                0.
+            } else {
+                (distance / SLOWDOWN_DISTANCE).clamp(-1., 1.) * ai.max_speed
+            };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intercept_without_bounce() {
+        let intercept = predict_intercept(Vec2::ZERO, Vec2::new(-1., 0.5), -100., -300., 300.);
+        assert_eq!(intercept, Some(50.));
+    }
+
+    #[test]
+    fn intercept_bounces_off_top_gutter() {
+        // Would reach 150 with no walls; the top wall at 100 folds it back to 50
+        let intercept = predict_intercept(Vec2::ZERO, Vec2::new(1., 1.5), 100., -100., 100.);
+        assert_eq!(intercept, Some(50.));
+    }
+
+    #[test]
+    fn intercept_bounces_off_both_gutters() {
+        // 350 up: off the top at 100, down through 200 to the bottom at -100,
+        // then back up another 50
+        let intercept = predict_intercept(Vec2::ZERO, Vec2::new(-1., 3.5), -100., -100., 100.);
+        assert_eq!(intercept, Some(-50.));
+    }
+
+    #[test]
+    fn intercept_without_walls_goes_straight() {
+        let intercept = predict_intercept(
+            Vec2::ZERO,
+            Vec2::new(1., 3.),
+            100.,
+            f32::NEG_INFINITY,
+            f32::INFINITY,
+        );
+        assert_eq!(intercept, Some(300.));
+    }
+
+    #[test]
+    fn no_intercept_when_heading_away() {
+        let intercept = predict_intercept(Vec2::ZERO, Vec2::new(1., 0.2), -100., -300., 300.);
+        assert_eq!(intercept, None);
+    }
+
+    #[test]
+    fn faster_ball_further_away_arrives_first() {
+        let near = time_to_reach(Vec2::new(-200., 0.), Vec2::new(-100., 0.), -400.);
+        let far = time_to_reach(Vec2::new(0., 0.), Vec2::new(-800., 50.), -400.);
+
+        assert_eq!(near, Some(2.));
+        assert_eq!(far, Some(0.5));
+        assert_eq!(time_to_reach(Vec2::ZERO, Vec2::new(300., 0.), -400.), None);
+        assert_eq!(time_to_reach(Vec2::ZERO, Vec2::ZERO, -400.), None);
+    }
+}
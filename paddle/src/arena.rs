@@ -0,0 +1,106 @@
+//! Arena module describing the playing field.
+//!
+//! The arena is the rectangle the game is played in, centered on the origin.
+//! Every system that needs to know where the edges of the field are reads the
+//! `Arena` resource instead of querying the window, which lets the game run
+//! without a window at all (for example in tests or bots).
+//!
+//! The arena is a fixed size in world units, whatever the size of the window.
+//! The camera scales it to fit the window and letterboxes it, filling whatever
+//! is left over on the sides or above and below with black bars, so resizing
+//! the window (or the browser, on the web) never moves anything in the arena.
+
+use bevy::{
+    asset::Assets,
+    color::Color,
+    core_pipeline::core_2d::Camera2d,
+    ecs::{
+        component::Component,
+        resource::Resource,
+        system::{Commands, Res, ResMut},
+    },
+    math::{primitives::Rectangle, Vec2},
+    render::{
+        camera::{ClearColor, OrthographicProjection, Projection, ScalingMode},
+        mesh::{Mesh, Mesh2d},
+    },
+    sprite::{ColorMaterial, MeshMaterial2d},
+    transform::components::Transform,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{accessibility::AccessibilitySettings, tuning::PaddleTuning};
+
+/// Color of the bars around the arena when the window's aspect ratio differs
+/// from the arena's.
+pub const LETTERBOX_COLOR: Color = Color::BLACK;
+
+/// Size of the playing field in world units.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Arena {
+    pub size: Vec2,
+}
+
+impl Arena {
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            size: Vec2::new(width, height),
+        }
+    }
+
+    pub fn half_width(&self) -> f32 {
+        self.size.x / 2.
+    }
+
+    pub fn half_height(&self) -> f32 {
+        self.size.y / 2.
+    }
+
+    /// Scales the arena to fill as much of the window as it can while keeping
+    /// its aspect ratio.
+    pub fn scaling_mode(&self) -> ScalingMode {
+        ScalingMode::AutoMin {
+            min_width: self.size.x,
+            min_height: self.size.y,
+        }
+    }
+}
+
+/// The arena's background, drawn in the palette's arena color.
+#[derive(Component)]
+pub struct Backdrop;
+
+/// Spawns the camera looking at the whole arena, and the backdrop that sets the
+/// arena apart from the letterbox around it.
+pub fn spawn_camera(
+    mut commands: Commands,
+    arena: Res<Arena>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    accessibility: Res<AccessibilitySettings>,
+    tuning: Res<PaddleTuning>,
+) {
+    commands.spawn((
+        Camera2d,
+        Projection::Orthographic(OrthographicProjection {
+            scaling_mode: arena.scaling_mode(),
+            ..OrthographicProjection::default_2d()
+        }),
+    ));
+
+    commands.spawn((
+        Backdrop,
+        Mesh2d(meshes.add(Rectangle::from_size(arena.size))),
+        MeshMaterial2d(materials.add(accessibility.palette.colors(&tuning).arena)),
+        Transform::from_xyz(0., 0., -10.),
+    ));
+    commands.insert_resource(ClearColor(LETTERBOX_COLOR));
+}
+
+impl Default for Arena {
+    /// Matches the default window resolution so the windowed game looks the
+    /// same as it did when geometry was read from the window.
+    fn default() -> Self {
+        Self::new(1280., 720.)
+    }
+}
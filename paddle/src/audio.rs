@@ -0,0 +1,159 @@
+//! Sound effects.
+//!
+//! The game has no sound files: every effect is a short tone synthesized by
+//! Bevy's `Pitch` source, so there is nothing to load and the same sounds play
+//! natively and on the web.
+//! - Paddle hits and gutter bounces play from the `BallCollided` events sent by
+//!   `physics::handle_collisions`, higher the faster the ball is going
+//! - Every `PointScored` plays a tone that rises for the player's points and
+//!   falls for everyone else's
+//!
+//! The volume and mute from the `Settings` are applied as the global volume as
+//! soon as they change, and pressing mute toggles the sound at any time.
+
+use std::time::Duration;
+
+use bevy::{
+    asset::{Assets, Handle},
+    audio::{
+        AudioPlayer, AudioSink, AudioSinkPlayback, GlobalVolume, Pitch, PlaybackSettings, Volume,
+    },
+    ecs::{
+        change_detection::DetectChanges,
+        event::EventReader,
+        query::With,
+        resource::Resource,
+        system::{Commands, Query, Res, ResMut},
+    },
+};
+use leafwing_input_manager::prelude::ActionState;
+
+use crate::{
+    components::{Paddle, PlayerSide, Speed},
+    game::PointScored,
+    input::Action,
+    physics::{BallCollided, CollisionKind},
+    settings::Settings,
+    tuning::PaddleTuning,
+};
+
+const PADDLE_HIT: (f32, Duration) = (440., Duration::from_millis(60));
+const GUTTER_BOUNCE: (f32, Duration) = (220., Duration::from_millis(40));
+const POINT: (f32, Duration) = (660., Duration::from_millis(250));
+
+/// Playback speed, and so pitch, of the point sound when anyone but the player
+/// scores.
+const POINT_LOST: f32 = 0.75;
+
+/// Highest a collision is pitched up, however fast the ball goes.
+const MAX_PITCH: f32 = 2.;
+
+#[derive(Resource)]
+pub struct Sounds {
+    paddle_hit: Handle<Pitch>,
+    gutter_bounce: Handle<Pitch>,
+    point: Handle<Pitch>,
+}
+
+pub fn setup(mut commands: Commands, mut pitches: ResMut<Assets<Pitch>>) {
+    let mut tone = |(frequency, duration)| pitches.add(Pitch::new(frequency, duration));
+    commands.insert_resource(Sounds {
+        paddle_hit: tone(PADDLE_HIT),
+        gutter_bounce: tone(GUTTER_BOUNCE),
+        point: tone(POINT),
+    });
+}
+
+/// How much a collision is pitched up for a ball going at `speed`: not at all
+/// at the serve speed, and more as the rally speeds up.
+pub fn pitch(speed: f32, tuning: &PaddleTuning) -> f32 {
+    (speed / Speed::serve(tuning).0).clamp(1., MAX_PITCH)
+}
+
+pub fn play_collisions(
+    mut commands: Commands,
+    sounds: Res<Sounds>,
+    tuning: Res<PaddleTuning>,
+    mut collided: EventReader<BallCollided>,
+) {
+    for collision in collided.read() {
+        let sound = match collision.kind {
+            CollisionKind::Paddle => &sounds.paddle_hit,
+            CollisionKind::Gutter | CollisionKind::Obstacle | CollisionKind::Brick => {
+                &sounds.gutter_bounce
+            }
+        };
+        commands.spawn((
+            AudioPlayer(sound.clone()),
+            PlaybackSettings::DESPAWN.with_speed(pitch(collision.speed, &tuning)),
+        ));
+    }
+}
+
+pub fn play_points(
+    mut commands: Commands,
+    sounds: Res<Sounds>,
+    mut scored: EventReader<PointScored>,
+    side: PlayerSide,
+) {
+    for point in scored.read() {
+        let speed = if point.by == side.scorer() {
+            1.
+        } else {
+            POINT_LOST
+        };
+        commands.spawn((
+            AudioPlayer(sounds.point.clone()),
+            PlaybackSettings::DESPAWN.with_speed(speed),
+        ));
+    }
+}
+
+/// Mutes the sound, or unmutes it, when either player presses mute.
+pub fn toggle_mute(
+    players: Query<&ActionState<Action>, With<Paddle>>,
+    mut settings: ResMut<Settings>,
+) {
+    if players
+        .iter()
+        .any(|action| action.just_pressed(&Action::Mute))
+    {
+        settings.muted = !settings.muted;
+    }
+}
+
+/// Sets the global volume from the settings, including sounds already playing.
+pub fn apply_volume(
+    settings: Res<Settings>,
+    mut global: ResMut<GlobalVolume>,
+    mut sinks: Query<&mut AudioSink>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    let volume = if settings.muted {
+        Volume::SILENT
+    } else {
+        Volume::Linear(settings.volume)
+    };
+    global.volume = volume;
+    for mut sink in &mut sinks {
+        sink.set_volume(volume);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn faster_balls_sound_higher() {
+        let tuning = PaddleTuning::default();
+        let serve = tuning.ball_speed;
+
+        assert_eq!(pitch(serve, &tuning), 1.);
+        assert!(pitch(serve * 1.5, &tuning) > pitch(serve * 1.2, &tuning));
+        assert_eq!(pitch(serve * 10., &tuning), MAX_PITCH);
+    }
+}
@@ -0,0 +1,419 @@
+//! Breakout, played with the same ball, paddle and physics as the rest of the
+//! game.
+//!
+//! In `GameMode::Breakout` the player's paddle guards the bottom edge, the
+//! other three are walled off by gutters, and a grid of bricks fills the top of
+//! the arena. Bricks are `Position` and `Shape` entities, so the ball reflects
+//! off whichever face `physics` reports it touched. Each hit takes a hit point
+//! off a brick, and a brick that runs out breaks for a point.
+//!
+//! The player has `LIVES` lives rather than an opponent scoring against them:
+//! losing the last ball in play costs one, and losing them all is game over.
+//! Breaking the last brick of a level ends the rally and the next level is laid
+//! out for the next serve; clearing the last level wins the game.
+//!
+//! Levels are grids of characters, one string per row of bricks, where a digit
+//! is a brick with that many hit points and `.` or a space leaves a gap. The
+//! game ships with `assets/levels.ron` built in; when drawn to a window it
+//! loads the file again, and natively it watches it for changes. Levels that do
+//! not parse into bricks above the serve are left out with a warning.
+
+use std::fmt;
+
+use bevy::{
+    asset::{Asset, Assets},
+    ecs::{
+        change_detection::{DetectChanges, Ref},
+        component::Component,
+        entity::Entity,
+        event::{EventReader, EventWriter},
+        query::{Added, With},
+        resource::Resource,
+        system::{Commands, Query, Res, ResMut},
+    },
+    log::{info, warn},
+    math::{primitives::Rectangle, Vec2},
+    reflect::TypePath,
+    render::mesh::{Mesh, Mesh2d},
+    sprite::{ColorMaterial, MeshMaterial2d},
+    state::state::NextState,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    accessibility::ArenaColors,
+    arena::Arena,
+    components::{Ball, Position, Shape},
+    game::{AppState, GameMode, MatchState, MatchWon, RallyEnded, Score, Scorer},
+    physics::{BallCollided, CollisionKind},
+    replay::Playback,
+    ron_asset::{Reloaded, RonAsset},
+    tuning::PaddleTuning,
+};
+
+/// Where the levels are loaded from, in the assets folder.
+pub const PATH: &str = "levels.ron";
+
+/// Balls the player can lose before the game is over.
+pub const LIVES: usize = 3;
+
+const BRICK_HEIGHT: f32 = 20.;
+/// Space left between neighbouring bricks.
+const BRICK_GAP: f32 = 4.;
+/// Space between the top gutter and the first row of bricks.
+const TOP_MARGIN: f32 = 60.;
+
+/// A grid of bricks to break.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Level {
+    pub name: String,
+    /// One string per row, from the top down.
+    pub rows: Vec<String>,
+}
+
+/// Why a level cannot be played.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LevelError {
+    UnknownBrick(char),
+    NoBricks,
+    /// The rows reach down to where the ball is served from.
+    TooLow,
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownBrick(brick) => write!(f, "{brick:?} is not a brick"),
+            Self::NoBricks => write!(f, "there are no bricks to break"),
+            Self::TooLow => write!(f, "the bricks reach down to the serve"),
+        }
+    }
+}
+
+impl Level {
+    /// Every brick of the level with where it stands, its size and its hit
+    /// points. Columns split the width between the side gutters evenly.
+    pub fn bricks(
+        &self,
+        arena: &Arena,
+        tuning: &PaddleTuning,
+    ) -> Result<Vec<(Vec2, Vec2, u32)>, LevelError> {
+        let columns = self
+            .rows
+            .iter()
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or(0);
+        let cell = Vec2::new(
+            (arena.size.x - tuning.gutter_height * 2.) / columns as f32,
+            BRICK_HEIGHT + BRICK_GAP,
+        );
+        let top_left = Vec2::new(
+            -arena.half_width() + tuning.gutter_height,
+            arena.half_height() - tuning.gutter_height - TOP_MARGIN,
+        );
+
+        let mut bricks = Vec::new();
+        for (row, line) in self.rows.iter().enumerate() {
+            for (column, brick) in line.chars().enumerate() {
+                let hit_points = match brick {
+                    '.' | ' ' => continue,
+                    _ => brick
+                        .to_digit(10)
+                        .filter(|&hit_points| hit_points > 0)
+                        .ok_or(LevelError::UnknownBrick(brick))?,
+                };
+                let center = top_left + Vec2::new(column as f32 + 0.5, -(row as f32 + 0.5)) * cell;
+                bricks.push((center, cell - BRICK_GAP, hit_points));
+            }
+        }
+
+        let lowest = bricks
+            .iter()
+            .map(|(center, size, _)| center.y - size.y / 2.)
+            .reduce(f32::min)
+            .ok_or(LevelError::NoBricks)?;
+        if lowest < tuning.ball_size * 2. {
+            return Err(LevelError::TooLow);
+        }
+        Ok(bricks)
+    }
+}
+
+/// The levels of a game, played in order.
+#[derive(Asset, Resource, TypePath, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Levels(pub Vec<Level>);
+
+impl Default for Levels {
+    /// The levels the game ships with.
+    fn default() -> Self {
+        ron::from_str(include_str!("../assets/levels.ron")).expect("the shipped levels parse")
+    }
+}
+
+impl RonAsset for Levels {
+    const PATH: &'static str = PATH;
+}
+
+/// Takes the levels that can be played from the file whenever it is loaded or
+/// changes, keeping the ones before if none of them can. A replay is played
+/// back through the levels it was recorded with instead.
+pub fn apply(
+    mut file: Reloaded<Levels>,
+    mut levels: ResMut<Levels>,
+    arena: Res<Arena>,
+    tuning: Res<PaddleTuning>,
+    playback: Option<Res<Playback>>,
+) {
+    let Some(loaded) = file.get() else {
+        return;
+    };
+    if playback.is_some() {
+        return;
+    }
+
+    let mut valid = Vec::new();
+    for level in &loaded.0 {
+        match level.bricks(&arena, &tuning) {
+            Ok(_) => valid.push(level.clone()),
+            Err(error) => warn!("Left out the {} level: {error}", level.name),
+        }
+    }
+    if valid.is_empty() {
+        warn!("No level in {PATH} can be played");
+        return;
+    }
+    info!("Loaded {} levels from {PATH}", valid.len());
+    *levels = Levels(valid);
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+#[require(Position, Shape)]
+pub struct Brick {
+    pub hit_points: u32,
+}
+
+/// Balls the player has left.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lives(pub usize);
+
+impl Default for Lives {
+    fn default() -> Self {
+        Self(LIVES)
+    }
+}
+
+/// Index of the level being played.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CurrentLevel(pub usize);
+
+/// The player breaking the bricks, numbered by the edge their paddle guards.
+pub fn player() -> Scorer {
+    GameMode::Breakout.player_edge().scorer()
+}
+
+pub fn enabled(mode: Res<GameMode>) -> bool {
+    *mode == GameMode::Breakout
+}
+
+/// Gives the player all their lives back and starts over from the first level.
+pub fn start(mut lives: ResMut<Lives>, mut level: ResMut<CurrentLevel>) {
+    *lives = Lives::default();
+    *level = CurrentLevel::default();
+}
+
+/// Replaces the bricks with the current level's whenever it changes. Outside
+/// of breakout this clears away any bricks left over.
+pub fn spawn_level(
+    mut commands: Commands,
+    level: Res<CurrentLevel>,
+    levels: Res<Levels>,
+    mode: Res<GameMode>,
+    arena: Res<Arena>,
+    tuning: Res<PaddleTuning>,
+    bricks: Query<Entity, With<Brick>>,
+) {
+    if !level.is_changed() {
+        return;
+    }
+    for entity in &bricks {
+        commands.entity(entity).despawn();
+    }
+    if *mode != GameMode::Breakout {
+        return;
+    }
+
+    let Some(current) = levels.0.get(level.0) else {
+        return;
+    };
+    match current.bricks(&arena, &tuning) {
+        Ok(bricks) => {
+            for (center, size, hit_points) in bricks {
+                commands.spawn((
+                    Brick { hit_points },
+                    Position { coords: center },
+                    Shape { size },
+                ));
+            }
+        }
+        Err(error) => warn!("Could not lay out the {} level: {error}", current.name),
+    }
+}
+
+/// Takes a hit point off every brick the ball bounced off this tick, breaking
+/// the ones that run out for a point each. Breaking the last brick ends the
+/// rally in the player's favour, down to a single ball.
+pub fn hit_bricks(
+    mut commands: Commands,
+    mut collided: EventReader<BallCollided>,
+    mut bricks: Query<(Entity, &mut Brick)>,
+    balls: Query<Entity, With<Ball>>,
+    mut score: ResMut<Score>,
+    mut ended: EventWriter<RallyEnded>,
+) {
+    let mut hit = false;
+    for collided in collided.read() {
+        if collided.kind != CollisionKind::Brick {
+            continue;
+        }
+        let Ok((entity, mut brick)) = bricks.get_mut(collided.wall) else {
+            continue;
+        };
+        if brick.hit_points == 0 {
+            // Already broken by another ball this tick
+            continue;
+        }
+        hit = true;
+        brick.hit_points -= 1;
+        if brick.hit_points == 0 {
+            commands.entity(entity).despawn();
+            score.points[player().index()] += 1;
+        }
+    }
+
+    if hit && bricks.iter().all(|(_, brick)| brick.hit_points == 0) {
+        for entity in balls.iter().skip(1) {
+            commands.entity(entity).despawn();
+        }
+        ended.write(RallyEnded { by: player() });
+    }
+}
+
+/// Ends the rally: a cleared level moves on to the next one, or wins the game
+/// after the last, and a lost ball costs a life, or the game with the last one.
+pub fn end_rally(
+    mut ended: EventReader<RallyEnded>,
+    mut lives: ResMut<Lives>,
+    mut level: ResMut<CurrentLevel>,
+    levels: Res<Levels>,
+    mut won: EventWriter<MatchWon>,
+    mut next_state: ResMut<NextState<MatchState>>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+) {
+    for ended in ended.read() {
+        next_state.set(MatchState::PointPause);
+
+        if ended.by == player() {
+            level.0 += 1;
+            if level.0 >= levels.0.len() {
+                won.write(MatchWon { by: ended.by });
+                next_app_state.set(AppState::GameOver);
+            }
+        } else {
+            lives.0 = lives.0.saturating_sub(1);
+            if lives.0 == 0 {
+                won.write(MatchWon { by: ended.by });
+                next_app_state.set(AppState::GameOver);
+            }
+        }
+    }
+}
+
+/// Attaches a mesh and material to every newly spawned brick.
+pub fn add_meshes(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    colors: ArenaColors,
+    bricks: Query<(Entity, &Brick), Added<Brick>>,
+) {
+    let colors = colors.get();
+    for (entity, brick) in &bricks {
+        let mesh = meshes.add(Rectangle::new(1., 1.));
+        let material = materials.add(colors.brick(brick.hit_points));
+        commands
+            .entity(entity)
+            .insert((Mesh2d(mesh), MeshMaterial2d(material)));
+    }
+}
+
+/// Repaints every brick that was hit, and all of them whenever the tuning or
+/// the palette changes.
+pub fn recolor(
+    colors: ArenaColors,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    bricks: Query<(Ref<Brick>, &MeshMaterial2d<ColorMaterial>)>,
+) {
+    let repaint_all = colors.is_changed();
+    let colors = colors.get();
+    for (brick, material) in &bricks {
+        if !(repaint_all || brick.is_changed()) {
+            continue;
+        }
+        if let Some(material) = materials.get_mut(material) {
+            material.color = colors.brick(brick.hit_points);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(rows: &[&str]) -> Level {
+        Level {
+            name: "Test".into(),
+            rows: rows.iter().map(|row| row.to_string()).collect(),
+        }
+    }
+
+    fn bricks(level: &Level) -> Result<Vec<(Vec2, Vec2, u32)>, LevelError> {
+        level.bricks(&Arena::default(), &PaddleTuning::default())
+    }
+
+    #[test]
+    fn shipped_levels_can_be_played() {
+        let levels = Levels::default();
+
+        assert!(!levels.0.is_empty());
+        for level in &levels.0 {
+            assert!(bricks(level).is_ok(), "{}", level.name);
+        }
+    }
+
+    #[test]
+    fn bricks_fill_the_width_between_the_gutters() {
+        let bricks = bricks(&level(&["1.3", "2"])).unwrap();
+        let arena = Arena::default();
+        let tuning = PaddleTuning::default();
+        let inner = arena.half_width() - tuning.gutter_height;
+
+        let hit_points: Vec<u32> = bricks.iter().map(|brick| brick.2).collect();
+        assert_eq!(hit_points, [1, 3, 2]);
+        let (first, size, _) = bricks[0];
+        assert!((first.x - size.x / 2. - BRICK_GAP / 2. + inner).abs() < 1e-3);
+        let (last, size, _) = bricks[1];
+        assert!((last.x + size.x / 2. + BRICK_GAP / 2. - inner).abs() < 1e-3);
+        // The second row sits right under the first
+        assert_eq!(bricks[2].0.y, first.y - BRICK_HEIGHT - BRICK_GAP);
+    }
+
+    #[test]
+    fn levels_have_to_be_made_of_bricks() {
+        assert_eq!(bricks(&level(&["1x1"])), Err(LevelError::UnknownBrick('x')));
+        assert_eq!(bricks(&level(&["1 0"])), Err(LevelError::UnknownBrick('0')));
+        assert_eq!(bricks(&level(&["...", ""])), Err(LevelError::NoBricks));
+        assert_eq!(bricks(&level(&["1"; 20])), Err(LevelError::TooLow));
+    }
+}
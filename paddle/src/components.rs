@@ -1,93 +1,145 @@
 //! Core game components and systems for the paddle game.
 //!
 //! This module contains the fundamental building blocks used to construct the game entities:
-//! - Position and velocity components for movement
+//! - Position and velocity components for movement, stepped on a fixed tick
+//!   and interpolated when rendered
 //! - Shape component for collision detection
-//! - Ball, Paddle, and Gutter entities and their associated systems
-//! - Spawn systems for creating game objects
+//! - Ball, Paddle, and Gutter entities and their associated systems, with a
+//!   record of which paddle last touched each ball
+//! - The `Edge` of the arena each paddle guards, which decides where it
+//!   stands, the axis it moves along and whose goal is behind it
+//! - Spawn systems for creating game objects, a system laying out the paddles
+//!   and gutters for the `GameMode`, and a system resizing them whenever the
+//!   `PaddleTuning` changes
+//! - Mesh systems that give spawned game objects something to draw, sized to
+//!   their shape and colored from the accessibility palette
 //!
 //! The components follow Bevy's ECS pattern and are designed to be composable.
 //! Systems in this module handle core game mechanics like paddle movement and
 //! ball physics.
 
-use bevy::{log, prelude::*};
-use config::{
-    BALL_COLOR, BALL_SIZE, BALL_SPEED, GUTTER_HEIGHT, PADDLE_HEIGHT, PADDLE_ONE_COLOR,
-    PADDLE_SPEED, PADDLE_TWO_COLOR, PADDLE_WIDTH,
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use crate::{
+    accessibility::ArenaColors,
+    ai::{Difficulty, OpponentAi},
+    arena::{Arena, Backdrop},
+    breakout::Brick,
+    game::{GameMode, Scorer},
+    input::PointerTarget,
+    layout::Obstacle,
+    powerup::Modifier,
+    tuning::PaddleTuning,
 };
 
-use crate::input::{self};
+/// How far in from its edge of the arena a paddle stands.
+const PADDLE_INSET: f32 = 50.;
 
-mod config {
-    use bevy::color::Color;
-
-    pub const PADDLE_ONE_COLOR: Color = Color::srgb(1.0, 1.0, 1.0);
-    pub const PADDLE_TWO_COLOR: Color = Color::srgb(0., 0., 1.);
-    pub const PADDLE_SPEED: f32 = 4.;
-
-    pub const BALL_SIZE: f32 = 5.0;
-    pub const BALL_COLOR: Color = Color::srgb(1.0, 0.0, 0.0);
-    pub const BALL_SPEED: f32 = 5.;
-
-    pub const PADDLE_WIDTH: f32 = 10.0;
-    pub const PADDLE_HEIGHT: f32 = 50.0;
-
-    pub const GUTTER_HEIGHT: f32 = 20.;
-}
-
-#[derive(Component, Default)]
-#[require(Transform)]
+#[derive(Component, Debug, Clone, Copy, Default)]
+#[require(Transform, PreviousPosition)]
 pub struct Position {
     pub coords: Vec2,
 }
 
+/// Where an entity was at the start of the current fixed tick. Rendering
+/// interpolates between this and `Position` so movement stays smooth when the
+/// frame rate and the tick rate differ.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct PreviousPosition(pub Vec2);
+
 impl Position {
-    pub fn project(mut positionables: Query<(&mut Transform, &Self)>) {
-        for (mut transform, position) in &mut positionables {
-            transform.translation = position.coords.extend(0.);
+    /// Remembers every position before the fixed tick moves anything.
+    pub fn remember(mut positionables: Query<(&Self, &mut PreviousPosition)>) {
+        for (position, mut previous) in &mut positionables {
+            previous.0 = position.coords;
+        }
+    }
+
+    /// Places every transform between its previous and current position, based
+    /// on how far the clock has run into the next fixed tick.
+    pub fn project(
+        mut positionables: Query<(&mut Transform, Ref<Self>, &mut PreviousPosition)>,
+        fixed_time: Res<Time<Fixed>>,
+    ) {
+        let alpha = fixed_time.overstep_fraction();
+        for (mut transform, position, mut previous) in &mut positionables {
+            // Freshly spawned entities have no history to interpolate from.
+            if position.is_added() {
+                previous.0 = position.coords;
+            }
+
+            let coords = previous.0.lerp(position.coords, alpha);
+            transform.translation = coords.extend(0.);
         }
     }
 }
 
-#[derive(Component, Default)]
+#[derive(Component, Debug, Clone, Copy, Default)]
 pub struct Velocity {
     pub direction: Vec2,
 }
 
-#[derive(Component, Default)]
+#[derive(Component, Debug, Clone, Copy, Default)]
 pub struct Shape {
     pub size: Vec2,
 }
 
+/// How fast the ball travels along its `Velocity` direction, in units per second.
+/// It ramps up with every paddle hit and drops back once a point is scored.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Speed(pub f32);
+
+impl Default for Speed {
+    /// The serve speed of the default tuning.
+    fn default() -> Self {
+        Self::serve(&PaddleTuning::default())
+    }
+}
+
+impl Speed {
+    /// How fast a ball is served.
+    pub fn serve(tuning: &PaddleTuning) -> Self {
+        Self(tuning.ball_speed)
+    }
+
+    pub fn ramp(&mut self, tuning: &PaddleTuning) {
+        self.0 = (self.0 + tuning.ball_speed_increment).min(tuning.ball_max_speed);
+    }
+}
+
+/// The paddle that last hit a ball, if any has since it was served.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LastTouched(pub Option<Entity>);
+
 #[derive(Component)]
 #[require(
     Position,
-    Velocity = Velocity { direction: Vec2::new(1., 1.)},
-    Shape = Shape { size: Vec2::new(BALL_SIZE, BALL_SIZE)}
+    Velocity = Velocity { direction: Vec2::new(1., 1.).normalize()},
+    Speed,
+    Shape = PaddleTuning::default().ball_shape(),
+    LastTouched
 )]
 pub struct Ball;
 
 impl Ball {
-    pub fn spawn(
-        mut commands: Commands,
-        mut meshes: ResMut<Assets<Mesh>>,
-        mut materials: ResMut<Assets<ColorMaterial>>,
-    ) {
-        let shape = Circle::new(BALL_SIZE);
-        let mesh = meshes.add(shape);
-        let material = materials.add(BALL_COLOR);
-
-        commands.spawn((Self, Mesh2d(mesh), MeshMaterial2d(material)));
+    pub fn spawn(mut commands: Commands, tuning: Res<PaddleTuning>) {
+        commands.spawn((Self, tuning.ball_shape(), Speed::serve(&tuning)));
     }
 
-    pub fn movement(mut ball: Query<(&mut Position, &Velocity), With<Self>>) -> Result {
-        let (mut position, velocity) = ball.single_mut()?;
-        position.coords += velocity.direction * BALL_SPEED;
-        Ok(())
+    /// Moves every ball in play, including any speed burst it was given.
+    pub fn movement(
+        mut balls: Query<(&mut Position, &Velocity, &Speed, Option<&Modifier>), With<Self>>,
+        time: Res<Time>,
+    ) {
+        for (mut position, velocity, speed, modifier) in &mut balls {
+            let speed = speed.0 * Modifier::speed_factor(modifier);
+            position.coords += velocity.direction * speed * time.delta_secs();
+        }
     }
 }
 
 #[derive(Component)]
+#[require(PointerTarget)]
 pub struct Player;
 
 #[derive(Component)]
@@ -97,127 +149,392 @@ pub struct Opponent;
 #[require(
     Position,
     Velocity,
-    Shape = Shape { size: Vec2::new(PADDLE_WIDTH, PADDLE_HEIGHT)}
+    Shape = Shape { size: PaddleTuning::default().paddle_size() }
 )]
 pub struct Paddle;
 
-pub fn spawn_paddles(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    window: Query<&Window>,
-) -> Result {
-    let window = window.single()?;
-
-    let width = window.resolution.width();
-    let padding = 50.0;
-    let right = width / 2.0 - padding;
-    let left = -width / 2.0 + padding;
-
-    let rect = Rectangle::new(PADDLE_WIDTH, PADDLE_HEIGHT);
-    let mesh = meshes.add(rect);
-    let player_one_color = materials.add(PADDLE_ONE_COLOR);
-    let player_two_color = materials.add(PADDLE_TWO_COLOR);
-
-    commands.spawn((
-        Player,
-        Paddle,
-        Position {
-            coords: Vec2::new(right, 0.),
-        },
-        input::setup_controls(),
-        Mesh2d(mesh.clone()),
-        MeshMaterial2d(player_one_color),
-    ));
+/// The edge of the arena a paddle guards. Every player has their own, in the
+/// order of their `Scorer` index: the player's on the right, the opponent's
+/// on the left, and in four player the top and then the bottom.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Edge {
+    Right,
+    Left,
+    Top,
+    Bottom,
+}
+
+impl Edge {
+    pub const ALL: [Self; 4] = [Self::Right, Self::Left, Self::Top, Self::Bottom];
 
-    commands.spawn((
-        Opponent,
+    /// The edge guarded by `player`.
+    pub fn of(player: Scorer) -> Self {
+        Self::ALL[player.index()]
+    }
+
+    /// The player guarding this edge.
+    pub fn scorer(self) -> Scorer {
+        Scorer::from(self)
+    }
+
+    pub fn opposite(self) -> Self {
+        match self {
+            Self::Right => Self::Left,
+            Self::Left => Self::Right,
+            Self::Top => Self::Bottom,
+            Self::Bottom => Self::Top,
+        }
+    }
+
+    /// Unit vector pointing out of the arena through this edge.
+    pub fn outward(self) -> Vec2 {
+        match self {
+            Self::Right => Vec2::X,
+            Self::Left => Vec2::NEG_X,
+            Self::Top => Vec2::Y,
+            Self::Bottom => Vec2::NEG_Y,
+        }
+    }
+
+    /// Unit vector along this edge, which its paddle moves along. Moving up
+    /// moves a paddle on the top or bottom edge to the right.
+    pub fn axis(self) -> Vec2 {
+        match self {
+            Self::Right | Self::Left => Vec2::Y,
+            Self::Top | Self::Bottom => Vec2::X,
+        }
+    }
+
+    /// Distance from the center of the arena to this edge.
+    pub fn distance(self, arena: &Arena) -> f32 {
+        match self {
+            Self::Right | Self::Left => arena.half_width(),
+            Self::Top | Self::Bottom => arena.half_height(),
+        }
+    }
+
+    /// Half the length of this edge.
+    pub fn half_length(self, arena: &Arena) -> f32 {
+        match self {
+            Self::Right | Self::Left => arena.half_height(),
+            Self::Top | Self::Bottom => arena.half_width(),
+        }
+    }
+
+    /// Whether `coords` is out of the arena through this edge.
+    pub fn is_past(self, coords: Vec2, arena: &Arena) -> bool {
+        coords.dot(self.outward()) > self.distance(arena)
+    }
+
+    /// Where this edge's paddle stands at the start of a match.
+    pub fn paddle_position(self, arena: &Arena) -> Vec2 {
+        self.outward() * (self.distance(arena) - PADDLE_INSET)
+    }
+
+    /// Turns the size of an upright paddle to lie along this edge.
+    pub fn orient(self, size: Vec2) -> Vec2 {
+        match self {
+            Self::Right | Self::Left => size,
+            Self::Top | Self::Bottom => size.yx(),
+        }
+    }
+}
+
+/// A paddle in the middle of `edge`, sized for it.
+pub fn paddle(edge: Edge, arena: &Arena, tuning: &PaddleTuning) -> impl Bundle {
+    (
         Paddle,
+        edge,
+        Shape {
+            size: edge.orient(tuning.paddle_size()),
+        },
         Position {
-            coords: Vec2::new(left, 0.),
+            coords: edge.paddle_position(arena),
         },
-        Mesh2d(mesh),
-        MeshMaterial2d(player_two_color),
-    ));
+    )
+}
 
-    Ok(())
+pub fn spawn_paddles(mut commands: Commands, arena: Res<Arena>, tuning: Res<PaddleTuning>) {
+    commands.spawn((Player, paddle(Edge::Right, &arena, &tuning)));
+    commands.spawn((Opponent, paddle(Edge::Left, &arena, &tuning)));
 }
 
-pub fn move_player_paddle(
-    mut paddle: Query<(&mut Position, &Velocity), With<Paddle>>,
-    window: Query<&Window>,
-) -> Result {
-    let window = window.single()?;
-    let window_height = window.resolution.height();
-    let max_y = (window_height / 2.) - GUTTER_HEIGHT - (PADDLE_HEIGHT / 2.);
-
-    for (mut position, velocity) in &mut paddle {
-        let new_position = position.coords + velocity.direction * PADDLE_SPEED;
-        if new_position.y.abs() < max_y {
-            position.coords = new_position;
+/// Lays the arena out for the game mode whenever it changes: the player's
+/// paddle moves to the mode's player edge, every other edge the mode guards
+/// gets an AI paddle unless it already has one, and the rest are walled off
+/// by gutters.
+pub fn lay_out(
+    mut commands: Commands,
+    mode: Res<GameMode>,
+    arena: Res<Arena>,
+    tuning: Res<PaddleTuning>,
+    difficulty: Res<Difficulty>,
+    paddles: Query<(Entity, &Edge, Has<Player>), With<Paddle>>,
+    gutters: Query<(Entity, &Gutter)>,
+) {
+    if !mode.is_changed() {
+        return;
+    }
+    let edges = mode.edges();
+    let player_edge = mode.player_edge();
+
+    for (entity, &edge, is_player) in &paddles {
+        if is_player && edge != player_edge {
+            commands
+                .entity(entity)
+                .insert(paddle(player_edge, &arena, &tuning));
+        } else if !is_player && (!edges.contains(&edge) || edge == player_edge) {
+            commands.entity(entity).despawn();
+        }
+    }
+    for &edge in edges {
+        if edge != player_edge && paddles.iter().all(|(_, paddle, _)| *paddle != edge) {
+            commands.spawn((
+                Opponent,
+                OpponentAi::new(*difficulty),
+                paddle(edge, &arena, &tuning),
+            ));
         }
     }
 
-    Ok(())
+    let walls = mode.walls();
+    for (entity, gutter) in &gutters {
+        if !walls.contains(&gutter.0) {
+            commands.entity(entity).despawn();
+        }
+    }
+    for &edge in walls {
+        if gutters.iter().all(|(_, gutter)| gutter.0 != edge) {
+            commands.spawn(Gutter::bundle(edge, &arena, &tuning));
+        }
+    }
 }
 
-pub fn move_opponent_paddle(
-    mut opponent: Query<(&Position, &mut Velocity), With<Opponent>>,
-    ball: Query<&Position, With<Ball>>,
+/// How far a paddle's center can move from the middle of its edge: up to the
+/// gutters, or with paddles on every edge, up to where the neighbouring
+/// paddles stand.
+fn reach(edge: Edge, shape: &Shape, arena: &Arena, tuning: &PaddleTuning, mode: GameMode) -> f32 {
+    let end = if mode.edges().contains(&Edge::Top) {
+        PADDLE_INSET + tuning.paddle_width / 2.
+    } else {
+        tuning.gutter_height
+    };
+    // Follows the paddle's current size, which power-ups can change
+    edge.half_length(arena) - end - shape.size.dot(edge.axis()) / 2.
+}
+
+pub fn move_player_paddle(
+    mut paddle: Query<(&mut Position, &Velocity, &Shape, &Edge), With<Paddle>>,
+    arena: Res<Arena>,
+    mode: Res<GameMode>,
+    tuning: Res<PaddleTuning>,
+    time: Res<Time>,
 ) {
-    if let Ok((position, mut velocity)) = opponent.get_single_mut() {
-        if let Ok(ball_position) = ball.get_single() {
-            let a_to_b = ball_position.coords - position.coords;
-            velocity.direction.y = a_to_b.y.signum();
+    for (mut position, velocity, shape, &edge) in &mut paddle {
+        let max = reach(edge, shape, &arena, &tuning, *mode);
+        let new_position =
+            position.coords + velocity.direction * tuning.paddle_speed * time.delta_secs();
+        let along = |coords: Vec2| coords.dot(edge.axis()).abs();
+        if along(new_position) < max {
+            position.coords = new_position;
+        } else if along(new_position) < along(position.coords) {
+            // A paddle that grew past the gutter can still move back out
+            position.coords = new_position;
         }
     }
 }
 
+/// A wall along the whole of an edge without a paddle.
 #[derive(Component)]
 #[require(Position, Shape)]
-struct Gutter;
+pub struct Gutter(pub Edge);
+
+impl Gutter {
+    /// Where the gutter along `edge` has its center.
+    fn position(edge: Edge, arena: &Arena, tuning: &PaddleTuning) -> Position {
+        // The gutter's origin is its center, so it stands half its height in
+        // from the edge to sit flush with it
+        Position {
+            coords: edge.outward() * (edge.distance(arena) - tuning.gutter_height / 2.),
+        }
+    }
+
+    fn shape(edge: Edge, arena: &Arena, tuning: &PaddleTuning) -> Shape {
+        Shape {
+            size: edge.outward().abs() * tuning.gutter_height
+                + edge.axis() * edge.half_length(arena) * 2.,
+        }
+    }
+
+    fn bundle(edge: Edge, arena: &Arena, tuning: &PaddleTuning) -> impl Bundle {
+        (
+            Self(edge),
+            Self::shape(edge, arena, tuning),
+            Self::position(edge, arena, tuning),
+        )
+    }
+}
 
 pub fn spawn_gutters(
+    mut commands: Commands,
+    mode: Res<GameMode>,
+    arena: Res<Arena>,
+    tuning: Res<PaddleTuning>,
+) {
+    for &edge in mode.walls() {
+        commands.spawn(Gutter::bundle(edge, &arena, &tuning));
+    }
+}
+
+/// Balls, kept apart from the paddles and gutters resized alongside them.
+type OnlyBalls = (With<Ball>, Without<Paddle>, Without<Gutter>);
+
+/// A paddle's size with what it depends on: its edge and any power-up.
+type SizedPaddle<'a> = (&'a mut Shape, &'a Edge, Option<&'a Modifier>);
+
+/// Resizes the balls, paddles and gutters whenever the tuning changes. Paddles
+/// keep any resizing power-up they have.
+pub fn apply_tuning(
+    tuning: Res<PaddleTuning>,
+    arena: Res<Arena>,
+    mut balls: Query<&mut Shape, OnlyBalls>,
+    mut paddles: Query<SizedPaddle, (With<Paddle>, Without<Gutter>)>,
+    mut gutters: Query<(&mut Shape, &mut Position, &Gutter)>,
+) {
+    if !tuning.is_changed() {
+        return;
+    }
+
+    for mut shape in &mut balls {
+        *shape = tuning.ball_shape();
+    }
+
+    for (mut shape, &edge, modifier) in &mut paddles {
+        shape.size = Modifier::paddle_size(modifier, edge, &tuning);
+    }
+
+    for (mut shape, mut position, gutter) in &mut gutters {
+        *shape = Gutter::shape(gutter.0, &arena, &tuning);
+        *position = Gutter::position(gutter.0, &arena, &tuning);
+    }
+}
+
+/// Attaches a mesh and material to every newly spawned ball, paddle and gutter.
+/// Spawning is kept free of rendering so the simulation can run headless; this
+/// system is only registered when the game is drawn to a window.
+pub fn add_meshes(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
-    window: Query<&Window>,
+    colors: ArenaColors,
+    balls: Query<Entity, Added<Ball>>,
+    paddles: Query<(Entity, Has<Player>), Added<Paddle>>,
+    gutters: Query<Entity, Added<Gutter>>,
+) {
+    let colors = colors.get();
+    // Meshes are a unit in size and scaled to each entity's `Shape`, so they
+    // follow any resizing
+    for entity in &balls {
+        let mesh = meshes.add(Circle::new(1.));
+        let material = materials.add(colors.ball);
+        commands
+            .entity(entity)
+            .insert((Mesh2d(mesh), MeshMaterial2d(material)));
+    }
+
+    for (entity, is_player) in &paddles {
+        let mesh = meshes.add(Rectangle::new(1., 1.));
+        let material = materials.add(colors.paddle(is_player));
+        commands
+            .entity(entity)
+            .insert((Mesh2d(mesh), MeshMaterial2d(material)));
+    }
+
+    for entity in &gutters {
+        let mesh = meshes.add(Rectangle::new(1., 1.));
+        let material = materials.add(colors.gutter);
+        commands
+            .entity(entity)
+            .insert((Mesh2d(mesh), MeshMaterial2d(material)));
+    }
+}
+
+/// Everything drawn with a unit mesh stretched to its `Shape`, when the shape
+/// just changed.
+type Resized = (
+    Changed<Shape>,
+    Or<(
+        With<Ball>,
+        With<Paddle>,
+        With<Gutter>,
+        With<Obstacle>,
+        With<Brick>,
+    )>,
+);
+
+/// The side of the arena the player plays on: the edge of their paddle. Online
+/// that is the side their session handed them, which may be either.
+#[derive(SystemParam)]
+pub struct PlayerSide<'w, 's> {
+    paddle: Query<'w, 's, &'static Edge, With<Player>>,
+    mode: Res<'w, GameMode>,
+}
+
+impl PlayerSide<'_, '_> {
+    pub fn edge(&self) -> Edge {
+        self.paddle
+            .single()
+            .map_or_else(|_| self.mode.player_edge(), |&edge| edge)
+    }
+
+    pub fn scorer(&self) -> Scorer {
+        self.edge().scorer()
+    }
+}
+
+/// Stretches the mesh of every ball, paddle, gutter and obstacle to its
+/// current size.
+pub fn scale_meshes(mut shapes: Query<(&Shape, &mut Transform, Has<Ball>), Resized>) {
+    for (shape, mut transform, is_ball) in &mut shapes {
+        transform.scale = if is_ball {
+            // A ball's size is its radius
+            Vec3::splat(shape.size.x)
+        } else {
+            shape.size.extend(1.)
+        };
+    }
+}
+
+/// Repaints the arena, balls, paddles and gutters whenever the tuning or the
+/// palette changes.
+pub fn recolor(
+    colors: ArenaColors,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    balls: Query<&MeshMaterial2d<ColorMaterial>, With<Ball>>,
+    paddles: Query<(&MeshMaterial2d<ColorMaterial>, Has<Player>), With<Paddle>>,
+    gutters: Query<&MeshMaterial2d<ColorMaterial>, With<Gutter>>,
+    backdrop: Query<&MeshMaterial2d<ColorMaterial>, With<Backdrop>>,
 ) {
-    if let Ok(window) = window.get_single() {
-        let window_width = window.resolution.width();
-        let window_height = window.resolution.height();
-
-        // We take half the window height because the center of our screen
-        // is (0, 0). The padding would be half the height of the gutter as its
-        // origin is also center rather than top left
-        let top_gutter_y = window_height / 2. - GUTTER_HEIGHT / 2.;
-        let bottom_gutter_y = -window_height / 2. + GUTTER_HEIGHT / 2.;
-
-        let shape = Rectangle::from_size(Vec2::new(window_width, GUTTER_HEIGHT));
-        let color = Color::srgb(0., 0., 0.);
-
-        // We can share these meshes between our gutters by cloning them
-        let mesh_handle = meshes.add(shape);
-        let material_handle = materials.add(color);
-
-        commands.spawn((
-            Gutter,
-            Shape { size: shape.size() },
-            Position {
-                coords: Vec2::new(0., top_gutter_y),
-            },
-            Mesh2d(mesh_handle.clone()),
-            MeshMaterial2d(material_handle.clone()),
-        ));
-
-        commands.spawn((
-            Gutter,
-            Shape { size: shape.size() },
-            Position {
-                coords: Vec2::new(0., bottom_gutter_y),
-            },
-            Mesh2d(mesh_handle),
-            MeshMaterial2d(material_handle),
-        ));
+    if !colors.is_changed() {
+        return;
+    }
+
+    let colors = colors.get();
+    let mut paint = |material: &MeshMaterial2d<ColorMaterial>, color| {
+        if let Some(material) = materials.get_mut(material) {
+            material.color = color;
+        }
+    };
+    for material in &balls {
+        paint(material, colors.ball);
+    }
+    for (material, is_player) in &paddles {
+        paint(material, colors.paddle(is_player));
+    }
+    for material in &gutters {
+        paint(material, colors.gutter);
+    }
+    for material in &backdrop {
+        paint(material, colors.arena);
     }
 }
@@ -0,0 +1,342 @@
+//! Visual effects that make hits and points feel punchy.
+//!
+//! This module provides:
+//! - A burst of particles where a ball touches a paddle or gutter, flying out of
+//!   the touched face
+//! - A short shake of the camera whenever a point is scored
+//! - A fading trail left behind every ball in play
+//! - A squash of a paddle's mesh when the ball hits it
+//! - The `EffectsSettings` resource turning each of these on or off
+//!
+//! With reduced motion in the `AccessibilitySettings`, the camera never shakes
+//! and paddles are never squashed, and particles fade where they burst.
+//! Particles and trails are drawn in the colors of the current palette.
+//!
+//! Effects only change what is drawn, never the simulation, and draw their
+//! randomness from the thread's generator rather than the `GameRng` so replays
+//! and online matches play out the same with or without them. A headless game
+//! has every effect turned off.
+
+use std::{f32::consts::FRAC_PI_3, time::Duration};
+
+use bevy::{
+    asset::{Assets, Handle},
+    core_pipeline::core_2d::Camera2d,
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::EventReader,
+        query::With,
+        resource::Resource,
+        system::{Commands, Query, Res, ResMut},
+    },
+    math::{primitives::Rectangle, Rot2, Vec2, Vec3},
+    render::mesh::{Mesh, Mesh2d},
+    sprite::{ColorMaterial, MeshMaterial2d},
+    time::{Time, Timer, TimerMode},
+    transform::components::Transform,
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    accessibility::{AccessibilitySettings, ArenaColors},
+    components::{Ball, Edge, Paddle, Shape, Velocity},
+    game::PointScored,
+    physics::{BallCollided, CollisionKind},
+};
+
+const PARTICLES_PER_HIT: usize = 8;
+const PARTICLE_SIZE: f32 = 3.;
+/// Units per second, before the random spread.
+const PARTICLE_SPEED: f32 = 180.;
+const PARTICLE_LIFETIME: Duration = Duration::from_millis(350);
+/// Widest angle, either side of the touched face's normal, particles fly out at.
+const PARTICLE_SPREAD: f32 = FRAC_PI_3;
+
+const SHAKE_DURATION: Duration = Duration::from_millis(250);
+/// Furthest the camera is pushed off center at the start of a shake, in units.
+const SHAKE_STRENGTH: f32 = 6.;
+
+/// How often a ball leaves a ghost of itself behind.
+const TRAIL_INTERVAL: Duration = Duration::from_millis(16);
+const TRAIL_LIFETIME: Duration = Duration::from_millis(150);
+
+const SQUASH_DURATION: Duration = Duration::from_millis(150);
+/// How much an upright paddle is squashed across and stretched along its
+/// length at the moment it is hit.
+const SQUASH: Vec2 = Vec2::new(-0.4, 0.15);
+
+/// Which visual effects are shown.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EffectsSettings {
+    pub particles: bool,
+    pub screen_shake: bool,
+    pub trail: bool,
+    pub squash: bool,
+}
+
+impl Default for EffectsSettings {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl EffectsSettings {
+    pub const ALL: Self = Self {
+        particles: true,
+        screen_shake: true,
+        trail: true,
+        squash: true,
+    };
+
+    pub const NONE: Self = Self {
+        particles: false,
+        screen_shake: false,
+        trail: false,
+        squash: false,
+    };
+}
+
+/// Meshes and materials shared by every particle and trail ghost.
+#[derive(Resource)]
+pub struct EffectAssets {
+    particle_mesh: Handle<Mesh>,
+    particle_material: Handle<ColorMaterial>,
+    trail_material: Handle<ColorMaterial>,
+}
+
+/// Shrinks away and despawns once its timer runs out, drifting as it goes.
+#[derive(Component)]
+pub struct Fading {
+    timer: Timer,
+    /// Units per second.
+    drift: Vec2,
+    /// Scale it starts shrinking from.
+    scale: Vec3,
+}
+
+impl Fading {
+    fn new(lifetime: Duration, drift: Vec2, scale: Vec3) -> Self {
+        Self {
+            timer: Timer::new(lifetime, TimerMode::Once),
+            drift,
+            scale,
+        }
+    }
+}
+
+/// A paddle springing back into shape after being hit.
+#[derive(Component)]
+pub struct Squash(Timer);
+
+/// Counts down the camera shake after a point.
+#[derive(Resource)]
+pub struct CameraShake(Timer);
+
+impl Default for CameraShake {
+    fn default() -> Self {
+        let mut timer = Timer::new(SHAKE_DURATION, TimerMode::Once);
+        // Nothing to shake until the first point
+        timer.tick(SHAKE_DURATION);
+        Self(timer)
+    }
+}
+
+/// Paces the ghosts each ball leaves behind.
+#[derive(Resource)]
+pub struct TrailTimer(Timer);
+
+impl Default for TrailTimer {
+    fn default() -> Self {
+        Self(Timer::new(TRAIL_INTERVAL, TimerMode::Repeating))
+    }
+}
+
+pub fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    colors: ArenaColors,
+) {
+    let colors = colors.get();
+    commands.insert_resource(EffectAssets {
+        particle_mesh: meshes.add(Rectangle::from_length(PARTICLE_SIZE)),
+        particle_material: materials.add(colors.particle),
+        trail_material: materials.add(colors.trail),
+    });
+}
+
+/// Repaints the particles and trails whenever the tuning or the palette
+/// changes.
+pub fn recolor(
+    colors: ArenaColors,
+    assets: Res<EffectAssets>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if !colors.is_changed() {
+        return;
+    }
+
+    let colors = colors.get();
+    if let Some(material) = materials.get_mut(&assets.particle_material) {
+        material.color = colors.particle;
+    }
+    if let Some(material) = materials.get_mut(&assets.trail_material) {
+        material.color = colors.trail;
+    }
+}
+
+/// Bursts particles out of the face every ball touched, and squashes the
+/// paddles that were hit.
+pub fn on_collision(
+    mut commands: Commands,
+    effects: Res<EffectsSettings>,
+    accessibility: Res<AccessibilitySettings>,
+    assets: Res<EffectAssets>,
+    mut collided: EventReader<BallCollided>,
+) {
+    let mut rng = rand::thread_rng();
+    for collision in collided.read() {
+        if effects.particles {
+            let normal = collision.with.normal();
+            for _ in 0..PARTICLES_PER_HIT {
+                let angle = rng.gen_range(-PARTICLE_SPREAD..=PARTICLE_SPREAD);
+                let speed = if accessibility.reduced_motion {
+                    0.
+                } else {
+                    PARTICLE_SPEED * rng.gen_range(0.5..=1.5)
+                };
+                commands.spawn((
+                    Mesh2d(assets.particle_mesh.clone()),
+                    MeshMaterial2d(assets.particle_material.clone()),
+                    Transform::from_translation(collision.point.extend(1.)),
+                    Fading::new(
+                        PARTICLE_LIFETIME,
+                        Rot2::radians(angle) * normal * speed,
+                        Vec3::ONE,
+                    ),
+                ));
+            }
+        }
+
+        if effects.squash
+            && !accessibility.reduced_motion
+            && collision.kind == CollisionKind::Paddle
+        {
+            commands
+                .entity(collision.wall)
+                .insert(Squash(Timer::new(SQUASH_DURATION, TimerMode::Once)));
+        }
+    }
+}
+
+/// Leaves a fading ghost behind every moving ball.
+pub fn leave_trail(
+    mut commands: Commands,
+    effects: Res<EffectsSettings>,
+    assets: Res<EffectAssets>,
+    mut timer: ResMut<TrailTimer>,
+    time: Res<Time>,
+    balls: Query<(&Transform, &Velocity, &Mesh2d), With<Ball>>,
+) {
+    if !effects.trail || !timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    for (transform, velocity, mesh) in &balls {
+        if velocity.direction == Vec2::ZERO {
+            continue;
+        }
+        commands.spawn((
+            mesh.clone(),
+            MeshMaterial2d(assets.trail_material.clone()),
+            // Under the ball itself
+            Transform::from_translation(transform.translation.truncate().extend(-0.5))
+                .with_scale(transform.scale),
+            Fading::new(TRAIL_LIFETIME, Vec2::ZERO, transform.scale),
+        ));
+    }
+}
+
+pub fn fade(
+    mut commands: Commands,
+    mut fading: Query<(Entity, &mut Fading, &mut Transform)>,
+    time: Res<Time>,
+) {
+    for (entity, mut fading, mut transform) in &mut fading {
+        if fading.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        transform.translation += (fading.drift * time.delta_secs()).extend(0.);
+        transform.scale = fading.scale * fading.timer.fraction_remaining();
+    }
+}
+
+/// Springs squashed paddles back to their shape, on top of any resizing
+/// power-up.
+pub fn unsquash(
+    mut commands: Commands,
+    mut paddles: Query<(Entity, &Shape, &Edge, &mut Squash, &mut Transform), With<Paddle>>,
+    time: Res<Time>,
+) {
+    for (entity, shape, edge, mut squash, mut transform) in &mut paddles {
+        let size = shape.size;
+        if squash.0.tick(time.delta()).finished() {
+            transform.scale = size.extend(1.);
+            commands.entity(entity).remove::<Squash>();
+            continue;
+        }
+        let squash = Vec2::ONE + edge.orient(SQUASH) * squash.0.fraction_remaining();
+        transform.scale = (size * squash).extend(1.);
+    }
+}
+
+pub fn start_shake(
+    effects: Res<EffectsSettings>,
+    accessibility: Res<AccessibilitySettings>,
+    mut shake: ResMut<CameraShake>,
+    mut scored: EventReader<PointScored>,
+) {
+    if scored.is_empty() {
+        return;
+    }
+    scored.clear();
+
+    if effects.screen_shake && !accessibility.reduced_motion {
+        shake.0.reset();
+    }
+}
+
+/// Jolts the camera around its resting place while a shake lasts, less and
+/// less as it runs out, and puts it back once it is over or motion is
+/// reduced.
+pub fn shake_camera(
+    effects: Res<EffectsSettings>,
+    accessibility: Res<AccessibilitySettings>,
+    mut shake: ResMut<CameraShake>,
+    mut camera: Query<&mut Transform, With<Camera2d>>,
+    time: Res<Time>,
+) {
+    shake.0.tick(time.delta());
+    let strength = if effects.screen_shake && !accessibility.reduced_motion && !shake.0.finished() {
+        SHAKE_STRENGTH * shake.0.fraction_remaining()
+    } else {
+        0.
+    };
+
+    let mut rng = rand::thread_rng();
+    for mut transform in &mut camera {
+        let offset = if strength > 0. {
+            Vec2::new(
+                rng.gen_range(-strength..=strength),
+                rng.gen_range(-strength..=strength),
+            )
+        } else {
+            Vec2::ZERO
+        };
+        transform.translation = offset.extend(transform.translation.z);
+    }
+}
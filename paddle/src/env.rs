@@ -0,0 +1,273 @@
+//! A gym-style environment for training and benchmarking paddle policies
+//! against the real game.
+//!
+//! `PaddleEnv` runs the `PaddlePlugin` headless under `MinimalPlugins`, so
+//! every episode is played by the same ball, paddle, physics and scoring
+//! systems as the game itself:
+//! - `reset` starts a new match with the `GameRng` seeded, so an episode plays
+//!   out the same for the same actions
+//! - `step` holds an action on the player's paddle for exactly one fixed tick,
+//!   pressing its movement actions the way replays do so it takes the same
+//!   path through `input::handle` as live input
+//! - The `Observation` after each step has the position and velocity, in units
+//!   per second, of every ball and paddle
+//! - The reward is 1 for every `PointScored` by the player during the tick and
+//!   -1 for every one scored by anyone else. Breakout and practice add to the
+//!   player's `Score` themselves, for every brick broken or shot returned, so
+//!   there the reward is 1 for every point added and -1 for every ball lost
+//! - The episode is done once the match is over
+//!
+//! The other paddles are played by the AI in single and four player, and held
+//! still in local versus. Online matches need a GGRS session and cannot be
+//! stepped here, so `reset` refuses them with `EnvError::Online`.
+
+use std::{fmt, time::Duration};
+
+use bevy::{
+    app::App,
+    ecs::{
+        entity::Entity,
+        event::{EventCursor, Events},
+        query::{Has, With},
+        world::World,
+    },
+    math::Vec2,
+    state::state::State,
+    time::{Fixed, Time, TimeUpdateStrategy},
+    MinimalPlugins,
+};
+use leafwing_input_manager::prelude::ActionState;
+
+use crate::{
+    ai::Difficulty,
+    arena::Arena,
+    components::{Ball, Edge, Paddle, Player, Position, Speed, Velocity},
+    game::{AppState, GameMode, GameRng, MatchRules, PointScored, Score},
+    input::{self, Action, MOVE_DOWN, MOVE_UP},
+    tuning::PaddleTuning,
+    PaddlePlugin,
+};
+
+/// Movement held on the player's paddle for a step. Up and down are along the
+/// edge the paddle guards, as with the keyboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Move {
+    #[default]
+    Stay,
+    Up,
+    Down,
+}
+
+impl Move {
+    /// The movement as a byte of `input::MOVE_UP` and `input::MOVE_DOWN` bits.
+    pub fn pack(self) -> u8 {
+        match self {
+            Self::Stay => 0,
+            Self::Up => MOVE_UP,
+            Self::Down => MOVE_DOWN,
+        }
+    }
+}
+
+/// Where something is and how fast it is moving, in units per second.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Body {
+    pub position: Vec2,
+    pub velocity: Vec2,
+}
+
+/// What a policy gets to see after every step.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Observation {
+    /// Every ball in play, of which there is at least one.
+    pub balls: Vec<Body>,
+    /// The paddle driven by the actions.
+    pub player: Body,
+    /// The other paddles with the edge each guards, in player order.
+    pub opponents: Vec<(Edge, Body)>,
+}
+
+/// Why an environment cannot start a match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvError {
+    /// Online matches need a GGRS session to advance.
+    Online,
+}
+
+impl fmt::Display for EnvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Online => write!(f, "online matches cannot be stepped without a GGRS session"),
+        }
+    }
+}
+
+pub struct PaddleEnv {
+    /// Size of the playing field.
+    pub arena: Arena,
+    pub mode: GameMode,
+    /// How good the AI paddles are.
+    pub difficulty: Difficulty,
+    pub rules: MatchRules,
+    app: Option<App>,
+    scored: EventCursor<PointScored>,
+}
+
+impl PaddleEnv {
+    /// An environment playing matches in `mode` with the default difficulty
+    /// and rules. Changes to its fields apply from the next `reset`.
+    pub fn new(arena: Arena, mode: GameMode) -> Self {
+        Self {
+            arena,
+            mode,
+            difficulty: Difficulty::default(),
+            rules: MatchRules::default(),
+            app: None,
+            scored: EventCursor::default(),
+        }
+    }
+
+    /// Starts a new match with the game's randomness seeded with `seed`, and
+    /// returns what it looks like before the first step.
+    ///
+    /// # Errors
+    ///
+    /// `EnvError::Online` if `mode` is `GameMode::OnlineVersus`, leaving the
+    /// previous match, if any, as it was.
+    pub fn reset(&mut self, seed: u64) -> Result<Observation, EnvError> {
+        if self.mode == GameMode::OnlineVersus {
+            return Err(EnvError::Online);
+        }
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(PaddlePlugin {
+                mode: self.mode,
+                difficulty: self.difficulty,
+                ..PaddlePlugin::headless(self.arena)
+            })
+            .insert_resource(GameRng::from_seed(seed))
+            .insert_resource(self.rules)
+            // One fixed tick per update
+            .insert_resource(TimeUpdateStrategy::ManualDuration(tick()));
+        // Sets the match up without running a tick yet
+        app.update();
+
+        self.scored = EventCursor::default();
+        self.app = Some(app);
+        Ok(self.observe())
+    }
+
+    /// Holds `action` on the player's paddle for one fixed tick. Returns what
+    /// the game looks like after it, the reward for the points scored during
+    /// it and whether the match is over.
+    ///
+    /// # Panics
+    ///
+    /// If `reset` has not been called yet.
+    pub fn step(&mut self, action: Move) -> (Observation, f32, bool) {
+        let app = self
+            .app
+            .as_mut()
+            .expect("the environment is reset before it is stepped");
+
+        let world = app.world_mut();
+        let mut player = world.query_filtered::<&mut ActionState<Action>, With<Player>>();
+        if let Ok(mut held) = player.single_mut(world) {
+            input::hold(&mut held, action.pack());
+        }
+        let player = self.mode.player_edge().scorer();
+        let points = |app: &App| app.world().resource::<Score>().points(player);
+        let before = points(app);
+        app.update();
+
+        let events = app.world().resource::<Events<PointScored>>();
+        let mut reward = self
+            .scored
+            .read(events)
+            .map(|scored| if scored.by == player { 1. } else { -1. })
+            .sum::<f32>();
+        if matches!(self.mode, GameMode::Breakout | GameMode::Practice) {
+            // Only ever scored against the player, who scores by the Score
+            reward += points(app).saturating_sub(before) as f32;
+        }
+        let done = *app.world().resource::<State<AppState>>().get() == AppState::GameOver;
+
+        (self.observe(), reward, done)
+    }
+
+    /// The game being played, for reading anything the observations leave
+    /// out, such as the `Score` or the `MatchStats`.
+    ///
+    /// # Panics
+    ///
+    /// If `reset` has not been called yet.
+    pub fn world(&self) -> &World {
+        self.app
+            .as_ref()
+            .expect("the environment is reset before it is looked at")
+            .world()
+    }
+
+    fn observe(&mut self) -> Observation {
+        let mode = self.mode;
+        let world = self
+            .app
+            .as_mut()
+            .expect("the environment is reset before it is looked at")
+            .world_mut();
+        let paddle_speed = world.resource::<PaddleTuning>().paddle_speed;
+
+        let mut balls = world
+            .query_filtered::<(Entity, &Position, &Velocity, &Speed), With<Ball>>()
+            .iter(world)
+            .map(|(entity, position, velocity, speed)| {
+                let body = Body {
+                    position: position.coords,
+                    velocity: velocity.direction * speed.0,
+                };
+                (entity, body)
+            })
+            .collect::<Vec<_>>();
+        // Balls keep their place in the list from one step to the next
+        balls.sort_by_key(|(entity, _)| *entity);
+
+        let mut observation = Observation {
+            balls: balls.into_iter().map(|(_, body)| body).collect(),
+            ..Default::default()
+        };
+        let mut paddles =
+            world.query_filtered::<(&Position, &Velocity, &Edge, Has<Player>), With<Paddle>>();
+        for (position, velocity, &edge, is_player) in paddles.iter(world) {
+            let body = Body {
+                position: position.coords,
+                velocity: velocity.direction * paddle_speed,
+            };
+            if is_player {
+                observation.player = body;
+            } else {
+                observation.opponents.push((edge, body));
+            }
+        }
+        observation
+            .opponents
+            .sort_by_key(|(edge, _)| mode.edges().iter().position(|other| other == edge));
+        observation
+    }
+}
+
+/// How long a fixed tick of the game lasts.
+fn tick() -> Duration {
+    Time::<Fixed>::default().timestep()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_pack_like_held_keys() {
+        assert_eq!(input::direction(Move::Stay.pack()), 0.);
+        assert_eq!(input::direction(Move::Up.pack()), 1.);
+        assert_eq!(input::direction(Move::Down.pack()), -1.);
+    }
+}
@@ -1,89 +1,806 @@
 //! Game state management and scoring system.
 //!
 //! This module handles the core game state including:
-//! - Score tracking for both players
-//! - Point scoring detection when ball goes past paddles
-//! - Ball reset logic after points
+//! - Score tracking for every player, by the index of their paddle
+//! - Point scoring detection for every ball that goes past a paddle, with the
+//!   rally lasting until the last ball in play is out
+//! - Ball reset logic after points, including dropping the rally's speed back down
+//! - Match rules deciding when a game, set and the whole match are won, and
+//!   how fast the ball is served
+//! - The `GameMode` deciding who controls the opponent's paddle, whether
+//!   paddles guard all four edges, and whether the player breaks bricks or
+//!   practises against a launcher instead
+//! - The `Serve` the ball waits for between rallies, where from, in which
+//!   direction and how fast
+//! - The `AppState` flow between the menus, playing, pausing and game over
+//! - The `MatchState` flow of serving, rallying and pausing after a point
 //! - Events for communicating scoring between systems
 //!
 //! The scoring system uses Bevy's event system to decouple point detection
 //! from score updates. The Score resource maintains the current game state.
 
+use std::time::Duration;
+
 use bevy::{
     ecs::{
-        error::Result,
+        entity::Entity,
         event::{Event, EventReader, EventWriter},
-        query::With,
+        query::{With, Without},
         resource::Resource,
-        system::{Query, ResMut},
+        system::{Commands, Query, Res, ResMut},
+        world::World,
     },
     math::Vec2,
-    window::Window,
+    state::state::{NextState, OnEnter, OnExit, OnTransition, State, StateSet, States, SubStates},
+    time::{Time, Timer, TimerMode},
+};
+use leafwing_input_manager::prelude::ActionState;
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arena::Arena,
+    components::{Ball, Edge, LastTouched, Paddle, Position, PreviousPosition, Speed, Velocity},
+    input::Action,
+    tuning::PaddleTuning,
 };
 
-use crate::components::{Ball, Position, Velocity};
+/// How long the ball waits in the center before it is served.
+const SERVE_DELAY: Duration = Duration::from_millis(1000);
+
+/// How long play stops after a point is scored.
+const POINT_PAUSE: Duration = Duration::from_millis(750);
+
+/// How far along the edge it is served towards the ball goes for every unit
+/// it goes towards it, with paddles on every edge. Those paddles are nearer
+/// the center, so the ball is served more squarely at them for them to have
+/// time to reach it.
+const FOUR_PLAYER_SERVE_SLANT: f32 = 0.25;
+
+/// Most players a match can have, one per edge of the arena.
+pub const MAX_PLAYERS: usize = 4;
 
-pub enum Scorer {
+/// A player, by the index of the paddle they play: see `Edge` for which edge
+/// each index guards. Every player guards an edge, so a `Scorer` is only made
+/// from one, with `Edge::scorer`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
+#[serde(from = "SavedScorer", into = "SavedScorer")]
+pub struct Scorer(usize);
+
+impl Scorer {
+    /// The player's own paddle, on the right.
+    pub const PLAYER: Self = Self(0);
+    /// The opponent's paddle, on the left.
+    pub const OPPONENT: Self = Self(1);
+
+    /// Where this player's points and sets are kept, always below
+    /// `MAX_PLAYERS`.
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+impl From<Edge> for Scorer {
+    fn from(edge: Edge) -> Self {
+        Self(edge as usize)
+    }
+}
+
+/// How a `Scorer` is saved: by name, so matches saved when there were only
+/// the player and the opponent still load.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+enum SavedScorer {
     Player,
     Opponent,
+    Top,
+    Bottom,
 }
 
-#[derive(Event)]
+impl From<SavedScorer> for Scorer {
+    fn from(saved: SavedScorer) -> Self {
+        Self(saved as usize)
+    }
+}
+
+impl From<Scorer> for SavedScorer {
+    fn from(scorer: Scorer) -> Self {
+        [Self::Player, Self::Opponent, Self::Top, Self::Bottom][scorer.index()]
+    }
+}
+
+/// Sent for every ball that leaves the arena past a paddle.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
 pub struct PointScored {
-    by: Scorer,
+    pub by: Scorer,
+}
+
+/// Sent when the last ball in play has left the arena, along with its point.
+#[derive(Event)]
+pub struct RallyEnded {
+    /// Who scored with the last ball.
+    pub by: Scorer,
+}
+
+/// Sent once when a side has won enough sets to take the match.
+#[derive(Event)]
+pub struct MatchWon {
+    pub by: Scorer,
+}
+
+/// Who controls the opponent's paddle, and how many paddles there are.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum GameMode {
+    /// The opponent's paddle is driven by the AI.
+    #[default]
+    SinglePlayer,
+    /// Two people play on one machine, one per paddle.
+    LocalVersus,
+    /// Two people play on their own machines, one per paddle, connected
+    /// through the `session` module.
+    OnlineVersus,
+    /// Paddles guard all four edges, each in front of its own goal. The player
+    /// plays the right paddle and the AI the other three.
+    FourPlayer,
+    /// The player's paddle guards the bottom edge against a wall of bricks,
+    /// with lives instead of an opponent; see the `breakout` module.
+    Breakout,
+    /// The player's paddle returns shots fired by a launcher in the wall
+    /// across the arena; see the `practice` module.
+    Practice,
+}
+
+impl GameMode {
+    /// The state a new match starts in; online matches first wait for an
+    /// opponent to connect.
+    pub fn starting_state(self) -> AppState {
+        match self {
+            Self::OnlineVersus => AppState::Matchmaking,
+            Self::SinglePlayer
+            | Self::LocalVersus
+            | Self::FourPlayer
+            | Self::Breakout
+            | Self::Practice => AppState::Playing,
+        }
+    }
+
+    /// The edges guarded by a paddle, in player order.
+    pub fn edges(self) -> &'static [Edge] {
+        match self {
+            Self::FourPlayer => &Edge::ALL,
+            Self::Breakout => &Edge::ALL[3..],
+            Self::Practice => &Edge::ALL[..1],
+            Self::SinglePlayer | Self::LocalVersus | Self::OnlineVersus => &Edge::ALL[..2],
+        }
+    }
+
+    /// The edges walled off by gutters, which are all those without a paddle.
+    pub fn walls(self) -> &'static [Edge] {
+        match self {
+            Self::FourPlayer => &[],
+            Self::Breakout => &Edge::ALL[..3],
+            Self::Practice => &Edge::ALL[1..],
+            Self::SinglePlayer | Self::LocalVersus | Self::OnlineVersus => &Edge::ALL[2..],
+        }
+    }
+
+    /// The edge the player's paddle guards, and the first serve heads for.
+    pub fn player_edge(self) -> Edge {
+        match self {
+            Self::Breakout => Edge::Bottom,
+            Self::SinglePlayer
+            | Self::LocalVersus
+            | Self::OnlineVersus
+            | Self::FourPlayer
+            | Self::Practice => Edge::Right,
+        }
+    }
+
+    /// Whether any paddle is played by the AI.
+    pub fn has_ai(self) -> bool {
+        matches!(self, Self::SinglePlayer | Self::FourPlayer)
+    }
+}
+
+#[derive(States, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[states(scoped_entities)]
+pub enum AppState {
+    /// The main menu, where the mode, difficulty and rules are picked.
+    #[default]
+    MainMenu,
+    /// An online match is waiting for an opponent to connect.
+    Matchmaking,
+    /// A match is being played.
+    Playing,
+    /// A match is on hold behind the pause menu.
+    Paused,
+    /// The match has been won and is waiting to be restarted.
+    GameOver,
+}
+
+/// Where the current match is at. It only exists while a match is being
+/// played or paused, and starts over at `Serving` with every new match.
+#[derive(SubStates, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[source(AppState = AppState::Playing | AppState::Paused)]
+pub enum MatchState {
+    /// The ball waits in the center for the serve delay to run out.
+    #[default]
+    Serving,
+    /// The ball is in play.
+    Rally,
+    /// A point was just scored and play stops briefly before the next serve.
+    PointPause,
+}
+
+/// Rules deciding when a match is over.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MatchRules {
+    /// Points needed to win a set.
+    pub points_to_win: usize,
+    /// Whether a set has to be won by a margin of two points.
+    pub win_by_two: bool,
+    /// Number of sets in the match; the first to win a majority takes it.
+    pub best_of: usize,
+    /// Whether power-ups appear during rallies.
+    #[serde(default)]
+    pub power_ups: bool,
+    /// Fraction of the serve speed the ball is served at.
+    #[serde(default = "full_speed")]
+    pub ball_speed: f32,
+}
+
+fn full_speed() -> f32 {
+    1.
+}
+
+impl Default for MatchRules {
+    fn default() -> Self {
+        Self {
+            points_to_win: 11,
+            win_by_two: true,
+            best_of: 1,
+            power_ups: false,
+            ball_speed: full_speed(),
+        }
+    }
 }
 
-#[derive(Resource, Default)]
+impl MatchRules {
+    pub fn sets_to_win(&self) -> usize {
+        self.best_of / 2 + 1
+    }
+
+    /// Returns who has won the current set with the given points, indexed by
+    /// player, if anyone. The winner has to lead every other player by the
+    /// margin.
+    pub fn set_winner(&self, points: &[usize]) -> Option<Scorer> {
+        let margin = if self.win_by_two { 2 } else { 1 };
+        let (&most, leader) = points
+            .iter()
+            .zip(Edge::ALL)
+            .max_by_key(|&(points, _)| points)?;
+        let runner_up = points
+            .iter()
+            .zip(Edge::ALL)
+            .filter(|&(_, edge)| edge != leader)
+            .map(|(&points, _)| points)
+            .max()
+            .unwrap_or(0);
+
+        (most >= self.points_to_win && most >= runner_up + margin).then_some(leader.scorer())
+    }
+}
+
+/// Points in the current set and sets won, indexed by player. Players a mode
+/// does not have stay at zero.
+#[derive(Resource, Debug, Clone, Copy, Default)]
 pub struct Score {
-    pub player: usize,
-    pub opponent: usize,
+    pub points: [usize; MAX_PLAYERS],
+    pub sets: [usize; MAX_PLAYERS],
+}
+
+impl Score {
+    pub fn points(&self, player: Scorer) -> usize {
+        self.points[player.index()]
+    }
+
+    pub fn sets(&self, player: Scorer) -> usize {
+        self.sets[player.index()]
+    }
+
+    /// Awards the set once the points say it has been won and starts the next
+    /// one. Returns who won the match when that set decided it, leaving the
+    /// deciding set's points on the board.
+    pub fn settle_set(&mut self, rules: &MatchRules) -> Option<Scorer> {
+        let set_winner = rules.set_winner(&self.points)?;
+
+        self.sets[set_winner.index()] += 1;
+        if self.sets[set_winner.index()] >= rules.sets_to_win() {
+            return Some(set_winner);
+        }
+
+        self.points = [0; MAX_PLAYERS];
+        None
+    }
+}
+
+/// How the ball will be launched once the serve delay runs out.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct Serve {
+    /// Where the ball waits for the serve.
+    pub from: Vec2,
+    pub direction: Vec2,
+    /// Speed the ball is served at, in units per second, or the tuning's
+    /// `ball_speed` when `None`.
+    pub speed: Option<f32>,
+}
+
+impl Default for Serve {
+    fn default() -> Self {
+        Self::toward(Edge::Right, GameMode::SinglePlayer)
+    }
+}
+
+impl Serve {
+    /// Serves the ball from the center at `edge`, at an angle up or to the
+    /// right along it, at the tuning's speed.
+    pub fn toward(edge: Edge, mode: GameMode) -> Self {
+        let slant = if mode == GameMode::FourPlayer {
+            FOUR_PLAYER_SERVE_SLANT
+        } else {
+            1.
+        };
+        Self {
+            from: Vec2::ZERO,
+            direction: (edge.outward() + edge.axis() * slant).normalize(),
+            speed: None,
+        }
+    }
+
+    /// Speed the ball waits at until it is served, slowed down by the rules'
+    /// `ball_speed`.
+    pub fn ball_speed(&self, tuning: &PaddleTuning, rules: &MatchRules) -> Speed {
+        let speed = self.speed.map_or_else(|| Speed::serve(tuning), Speed);
+        Speed(speed.0 * rules.ball_speed)
+    }
+}
+
+/// Source of randomness for everything that affects the simulation. It is
+/// seeded so a game can be played out again exactly.
+#[derive(Resource)]
+pub struct GameRng {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl GameRng {
+    pub fn from_seed(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::from_seed(rand::random())
+    }
 }
 
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+/// Times the serve delay and the pause after a point.
+#[derive(Resource, Debug, Clone)]
+pub struct Countdown(pub Timer);
+
+impl Default for Countdown {
+    fn default() -> Self {
+        Self(Timer::new(SERVE_DELAY, TimerMode::Once))
+    }
+}
+
+/// Scores a point for every ball that has left the arena past a paddle. Balls
+/// are removed as they leave, except for the last one: the rally goes on as
+/// long as any ball is still in play.
+///
+/// The point goes to whoever last touched the ball, unless that was the
+/// paddle it went past or no paddle touched it at all, in which case it goes
+/// to the paddle across the arena. With two paddles, that is always the
+/// other one.
 pub fn detect_scoring(
-    mut ball: Query<&mut Position, With<Ball>>,
-    window: Query<&Window>,
-    mut events: EventWriter<PointScored>,
-) -> Result {
-    let window = window.single()?;
-    let width = window.resolution.width();
+    mut commands: Commands,
+    balls: Query<(Entity, &Position, &LastTouched), With<Ball>>,
+    paddles: Query<&Edge, With<Paddle>>,
+    arena: Res<Arena>,
+    mode: Res<GameMode>,
+    mut scored: EventWriter<PointScored>,
+    mut rally_ended: EventWriter<RallyEnded>,
+) {
+    let mut in_play = balls.iter().len();
+    for (entity, ball, last_touched) in &balls {
+        let Some(goal) = mode
+            .edges()
+            .iter()
+            .find(|edge| edge.is_past(ball.coords, &arena))
+        else {
+            continue;
+        };
+        let by = match last_touched.0.and_then(|paddle| paddles.get(paddle).ok()) {
+            Some(toucher) if toucher != goal => toucher.scorer(),
+            _ => goal.opposite().scorer(),
+        };
 
-    let ball = ball.single_mut()?;
-    if ball.coords.x > width / 2. {
-        events.write(PointScored {
-            by: Scorer::Opponent,
-        });
-    } else if ball.coords.x < -width / 2. {
-        events.write(PointScored { by: Scorer::Player });
+        scored.write(PointScored { by });
+        in_play -= 1;
+        if in_play == 0 {
+            rally_ended.write(RallyEnded { by });
+        } else {
+            commands.entity(entity).despawn();
+        }
     }
+}
 
-    Ok(())
+/// Serves the next rally towards whoever scored last.
+pub fn next_serve(
+    mut serve: ResMut<Serve>,
+    mut events: EventReader<RallyEnded>,
+    mode: Res<GameMode>,
+) {
+    for ended in events.read() {
+        *serve = Serve::toward(Edge::of(ended.by), *mode);
+    }
 }
 
+/// Puts the ball back where the next serve is taken from once the rally is
+/// over, at the speed it is served at, and holds it there until it is served.
 pub fn reset_ball(
-    mut ball: Query<(&mut Position, &mut Velocity), With<Ball>>,
-    mut events: EventReader<PointScored>,
-) -> Result {
-    for scored in events.read() {
-        let (mut position, mut velocity) = ball.single_mut()?;
-        match scored.by {
-            Scorer::Opponent => {
-                position.coords = Vec2::new(0., 0.);
-                velocity.direction = Vec2::new(-1., 1.);
-            }
-            Scorer::Player => {
-                position.coords = Vec2::new(0., 0.);
-                velocity.direction = Vec2::new(1., 1.);
-            }
-        }
+    mut balls: Query<
+        (
+            &mut Position,
+            &mut PreviousPosition,
+            &mut Velocity,
+            &mut Speed,
+            &mut LastTouched,
+        ),
+        With<Ball>,
+    >,
+    serve: Res<Serve>,
+    mut events: EventReader<RallyEnded>,
+    tuning: Res<PaddleTuning>,
+    rules: Res<MatchRules>,
+) {
+    if events.is_empty() {
+        return;
+    }
+    events.clear();
+
+    for (mut position, mut previous, mut velocity, mut speed, mut last_touched) in &mut balls {
+        position.coords = serve.from;
+        velocity.direction = Vec2::ZERO;
+        *speed = serve.ball_speed(&tuning, &rules);
+        last_touched.0 = None;
+        // Teleport rather than sweep across the arena when rendered.
+        previous.0 = position.coords;
     }
-    Ok(())
 }
 
 pub fn update_score(mut score: ResMut<Score>, mut events: EventReader<PointScored>) {
     for scored in events.read() {
-        match scored.by {
-            Scorer::Opponent => score.opponent += 1,
-            Scorer::Player => score.player += 1,
-        }
+        score.points[scored.by.index()] += 1;
+    }
+}
+
+/// Ends the rally once the last ball is out, awarding the set and the match
+/// when the rules say they have been won.
+pub fn end_rally(
+    mut score: ResMut<Score>,
+    rules: Res<MatchRules>,
+    mut ended: EventReader<RallyEnded>,
+    mut won: EventWriter<MatchWon>,
+    mut next_state: ResMut<NextState<MatchState>>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+) {
+    if ended.is_empty() {
+        return;
+    }
+    ended.clear();
+
+    next_state.set(MatchState::PointPause);
+
+    if let Some(winner) = score.settle_set(&rules) {
+        won.write(MatchWon { by: winner });
+        next_app_state.set(AppState::GameOver);
+    }
+}
+
+pub fn start_serve_delay(mut countdown: ResMut<Countdown>) {
+    countdown.0 = Timer::new(SERVE_DELAY, TimerMode::Once);
+}
+
+pub fn start_point_pause(mut countdown: ResMut<Countdown>) {
+    countdown.0 = Timer::new(POINT_PAUSE, TimerMode::Once);
+}
+
+/// Launches the ball once the serve delay has run out.
+pub fn serve(
+    mut balls: Query<&mut Velocity, With<Ball>>,
+    mut countdown: ResMut<Countdown>,
+    serve: Res<Serve>,
+    time: Res<Time>,
+    mut next_state: ResMut<NextState<MatchState>>,
+) {
+    if !countdown.0.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    for mut velocity in &mut balls {
+        velocity.direction = serve.direction;
+    }
+    next_state.set(MatchState::Rally);
+}
+
+pub fn resume_after_point(
+    mut countdown: ResMut<Countdown>,
+    time: Res<Time>,
+    mut next_state: ResMut<NextState<MatchState>>,
+) {
+    if countdown.0.tick(time.delta()).just_finished() {
+        next_state.set(MatchState::Serving);
+    }
+}
+
+/// Serves the first rally of a match towards the player.
+pub fn first_serve(mut serve: ResMut<Serve>, mode: Res<GameMode>) {
+    *serve = Serve::toward(mode.player_edge(), *mode);
+}
+
+/// Everything about a ball that is put back before it is served.
+type ServedBall<'a> = (
+    Entity,
+    &'a mut Position,
+    &'a mut PreviousPosition,
+    &'a mut Velocity,
+    &'a mut Speed,
+    &'a mut LastTouched,
+);
+
+/// Clears the score and puts the ball back where the first serve is taken
+/// from before a new match, wherever the last one was left, down to a single
+/// ball. Every match starts from the same place so it can be replayed.
+pub fn start_match(
+    mut commands: Commands,
+    mut balls: Query<ServedBall, With<Ball>>,
+    mut score: ResMut<Score>,
+    serve: Res<Serve>,
+    tuning: Res<PaddleTuning>,
+    rules: Res<MatchRules>,
+) {
+    *score = Score::default();
+
+    let mut balls = balls.iter_mut();
+    if let Some((_, mut position, mut previous, mut velocity, mut speed, mut last_touched)) =
+        balls.next()
+    {
+        position.coords = serve.from;
+        previous.0 = position.coords;
+        velocity.direction = Vec2::ZERO;
+        *speed = serve.ball_speed(&tuning, &rules);
+        last_touched.0 = None;
+    }
+    for (entity, ..) in balls {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// A paddle's motion, which is reset along the edge it guards.
+type ResetPaddle<'a> = (
+    &'a mut Position,
+    &'a mut PreviousPosition,
+    &'a mut Velocity,
+    &'a Edge,
+);
+
+/// Puts every paddle back in the middle of its edge before a new match.
+pub fn reset_paddles(
+    mut paddles: Query<ResetPaddle, (With<Paddle>, Without<Ball>)>,
+    arena: Res<Arena>,
+) {
+    for (mut position, mut previous, mut velocity, edge) in &mut paddles {
+        position.coords = edge.paddle_position(&arena);
+        previous.0 = position.coords;
+        velocity.direction = Vec2::ZERO;
+    }
+}
+
+/// Applies the `MatchState` change asked for during the last fixed tick before
+/// the next one runs.
+///
+/// Bevy only applies state changes once per frame, so how many ticks a match
+/// spends serving, rallying or pausing after a point would depend on how the
+/// ticks happened to fall into frames, and a replay watched at another frame
+/// rate would drift from the match it recorded. Only the `MatchState` the
+/// simulation steps through is applied per tick; `AppState` changes such as
+/// game over still wait for the frame, so menus are spawned and despawned
+/// once, outside of the fixed loop.
+pub fn apply_match_state_transition(world: &mut World) {
+    let NextState::Pending(entered) = world
+        .get_resource_mut::<NextState<MatchState>>()
+        .map(|mut next| std::mem::take(&mut *next))
+        .unwrap_or_default()
+    else {
+        return;
+    };
+    // Outside of a match there is nothing to transition, and the change is
+    // dropped like Bevy would
+    let Some(exited) = world
+        .get_resource::<State<MatchState>>()
+        .map(|state| *state.get())
+    else {
+        return;
+    };
+    if exited == entered {
+        return;
+    }
+
+    world.insert_resource(State::new(entered));
+    let _ = world.try_run_schedule(OnExit(exited));
+    let _ = world.try_run_schedule(OnTransition { exited, entered });
+    let _ = world.try_run_schedule(OnEnter(entered));
+}
+
+/// Whether a tick earlier in this frame decided the match. The simulation
+/// holds still from then on, rather than for however many ticks are left in
+/// the frame until it is let into `AppState::GameOver`.
+pub fn match_decided(next_state: Res<NextState<AppState>>) -> bool {
+    matches!(*next_state, NextState::Pending(AppState::GameOver))
+}
+
+/// Starts a fresh match when either player asks for a restart.
+pub fn restart(
+    players: Query<&ActionState<Action>, With<Paddle>>,
+    mode: Res<GameMode>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if players
+        .iter()
+        .any(|action| action.just_pressed(&Action::Restart))
+    {
+        next_state.set(mode.starting_state());
+    }
+}
+
+/// Pauses the match, or resumes it, when either player presses pause.
+pub fn toggle_pause(
+    players: Query<&ActionState<Action>, With<Paddle>>,
+    state: Res<State<AppState>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if !players
+        .iter()
+        .any(|action| action.just_pressed(&Action::Pause))
+    {
+        return;
+    }
+
+    match state.get() {
+        AppState::Playing => next_state.set(AppState::Paused),
+        AppState::Paused => next_state.set(AppState::Playing),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_is_won_at_points_to_win() {
+        let rules = MatchRules {
+            points_to_win: 5,
+            win_by_two: false,
+            best_of: 1,
+            ..Default::default()
+        };
+
+        assert_eq!(rules.set_winner(&[4, 4]), None);
+        assert_eq!(rules.set_winner(&[5, 4]), Some(Scorer::PLAYER));
+        assert_eq!(rules.set_winner(&[3, 5]), Some(Scorer::OPPONENT));
+    }
+
+    #[test]
+    fn set_has_to_be_won_by_two() {
+        let rules = MatchRules {
+            points_to_win: 11,
+            win_by_two: true,
+            best_of: 1,
+            ..Default::default()
+        };
+
+        assert_eq!(rules.set_winner(&[11, 10]), None);
+        assert_eq!(rules.set_winner(&[12, 10]), Some(Scorer::PLAYER));
+        assert_eq!(rules.set_winner(&[14, 15]), None);
+        assert_eq!(rules.set_winner(&[14, 16]), Some(Scorer::OPPONENT));
+    }
+
+    #[test]
+    fn set_winner_has_to_lead_every_other_player() {
+        let rules = MatchRules {
+            points_to_win: 5,
+            win_by_two: true,
+            best_of: 1,
+            ..Default::default()
+        };
+
+        assert_eq!(rules.set_winner(&[5, 0, 4, 1]), None);
+        assert_eq!(rules.set_winner(&[5, 0, 3, 1]), Some(Scorer::PLAYER));
+        assert_eq!(rules.set_winner(&[2, 1, 0, 6]), Some(Edge::Bottom.scorer()));
+    }
+
+    #[test]
+    fn scorers_are_saved_as_they_were_with_two_players() {
+        assert_eq!(ron::to_string(&Scorer::PLAYER).unwrap(), "Player");
+        assert_eq!(
+            ron::from_str::<Scorer>("Opponent").unwrap(),
+            Scorer::OPPONENT
+        );
+
+        let bottom = Edge::Bottom.scorer();
+        let text = ron::to_string(&bottom).unwrap();
+        assert_eq!(ron::from_str::<Scorer>(&text).unwrap(), bottom);
+    }
+
+    #[test]
+    fn match_needs_a_majority_of_sets() {
+        let best_of = |best_of| MatchRules {
+            best_of,
+            ..Default::default()
+        };
+
+        assert_eq!(best_of(1).sets_to_win(), 1);
+        assert_eq!(best_of(3).sets_to_win(), 2);
+        assert_eq!(best_of(5).sets_to_win(), 3);
+    }
+
+    #[test]
+    fn winning_the_last_set_wins_the_match() {
+        let rules = MatchRules {
+            points_to_win: 5,
+            win_by_two: false,
+            best_of: 3,
+            ..Default::default()
+        };
+        let mut score = Score {
+            points: [5, 2, 0, 0],
+            ..Default::default()
+        };
+
+        assert_eq!(score.settle_set(&rules), None);
+        assert_eq!(score.points, [0; MAX_PLAYERS]);
+        assert_eq!(score.sets(Scorer::PLAYER), 1);
+
+        score.points[Scorer::PLAYER.index()] = 5;
+        assert_eq!(score.settle_set(&rules), Some(Scorer::PLAYER));
+        assert_eq!(score.points(Scorer::PLAYER), 5);
+        assert_eq!(score.sets(Scorer::PLAYER), 2);
     }
 }
@@ -2,59 +2,472 @@
 //!
 //! This module manages player input using the `leafwing_input_manager` crate.
 //! It provides:
-//! - Action enum defining possible player inputs (up/down movement)
-//! - Input mapping configuration for keyboard controls
+//! - Action enum defining possible player inputs (up/down movement, restarting a finished match,
+//!   pausing, muting the sound)
+//! - Input mapping configuration for keyboard and gamepad controls, per paddle,
+//!   with rebindable keys kept in the player's `Settings`
+//! - A system handing out input maps to the paddles for the selected `GameMode`
 //! - Input handling system to update paddle velocity based on player input
+//! - Packing of held movement into a byte, as replays record it and online
+//!   matches send it to the other player
+//! - Touch and mouse control of the player's paddle, which follows a finger
+//!   dragged across the screen or the mouse cursor
 //!
 //! The input system uses Bevy's ECS pattern and integrates with the game's
-//! velocity-based movement system. Both arrow keys and WASD controls are supported.
+//! velocity-based movement system. In single player both arrow keys and WASD
+//! drive the player's paddle; in local versus the left paddle uses W/S and the
+//! right paddle the arrow keys. Each side also gets its own gamepad.
+//!
+//! A touch or mouse only says where the paddle should go. Each tick, the paddle
+//! is moved up or down towards it as if the movement keys were held, so it
+//! moves no faster than with the keyboard, stops at the gutters the same way,
+//! and is recorded in replays and sent online like any other input. Pressing a
+//! movement key hands the paddle back to the keyboard until the pointer moves
+//! again.
+
+use std::time::Duration;
 
 use bevy::{
-    ecs::{query::With, system::Query},
-    input::keyboard::KeyCode,
+    core_pipeline::core_2d::Camera2d,
+    ecs::{
+        change_detection::DetectChanges,
+        component::Component,
+        entity::Entity,
+        event::EventReader,
+        query::{With, Without},
+        system::{Commands, Query, Res, SystemParam},
+    },
+    input::{
+        gamepad::{Gamepad, GamepadButton},
+        keyboard::KeyCode,
+        touch::Touches,
+        ButtonInput,
+    },
+    math::Vec2,
     reflect::Reflect,
+    render::camera::Camera,
+    time::{Fixed, Time},
+    transform::components::GlobalTransform,
+    window::{CursorLeft, CursorMoved},
 };
 use leafwing_input_manager::{
-    prelude::{ActionState, InputMap},
+    prelude::{ActionState, GamepadControlDirection, InputMap},
     Actionlike, InputManagerBundle,
 };
+use serde::{Deserialize, Serialize};
 
-use crate::components::{Player, Velocity};
+use crate::{
+    components::{Edge, Opponent, Paddle, Player, Position, Velocity},
+    game::GameMode,
+    settings::Settings,
+    tuning::PaddleTuning,
+};
+
+/// Bit set in a packed movement byte while moving up.
+pub const MOVE_UP: u8 = 1 << 0;
+/// Bit set in a packed movement byte while moving down.
+pub const MOVE_DOWN: u8 = 1 << 1;
 
 // This is the list of "things in the game I want to be able to do based on input"
-#[derive(Actionlike, PartialEq, Eq, Hash, Clone, Copy, Debug, Reflect)]
+#[derive(Actionlike, PartialEq, Eq, Hash, Clone, Copy, Debug, Reflect, Serialize, Deserialize)]
 pub enum Action {
     MoveUp,
     MoveDown,
+    Restart,
+    Pause,
+    Mute,
+}
+
+/// Keyboard bindings for each paddle, for each way it can be controlled.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    /// The player's paddle in single player.
+    pub single_player: Vec<(Action, KeyCode)>,
+    /// The left paddle in local versus.
+    pub left: Vec<(Action, KeyCode)>,
+    /// The right paddle in local versus.
+    pub right: Vec<(Action, KeyCode)>,
+    /// The player's paddle in breakout, which moves right on `MoveUp` and
+    /// left on `MoveDown`.
+    pub breakout: Vec<(Action, KeyCode)>,
+}
+
+impl Default for KeyBindings {
+    /// In single player the up and down arrow keys and W/S move the paddle, R
+    /// or Enter restart a finished match, Escape or P pause and M mutes. In
+    /// local versus the left paddle gets W/S, R, P and M, and the right paddle
+    /// the arrow keys, Enter and Escape. In breakout the left and right arrow
+    /// keys and A/D move the paddle, with the single player keys for the rest.
+    fn default() -> Self {
+        Self {
+            single_player: vec![
+                (Action::MoveUp, KeyCode::ArrowUp),
+                (Action::MoveUp, KeyCode::KeyW),
+                (Action::MoveDown, KeyCode::ArrowDown),
+                (Action::MoveDown, KeyCode::KeyS),
+                (Action::Restart, KeyCode::KeyR),
+                (Action::Restart, KeyCode::Enter),
+                (Action::Pause, KeyCode::Escape),
+                (Action::Pause, KeyCode::KeyP),
+                (Action::Mute, KeyCode::KeyM),
+            ],
+            left: vec![
+                (Action::MoveUp, KeyCode::KeyW),
+                (Action::MoveDown, KeyCode::KeyS),
+                (Action::Restart, KeyCode::KeyR),
+                (Action::Pause, KeyCode::KeyP),
+                (Action::Mute, KeyCode::KeyM),
+            ],
+            right: vec![
+                (Action::MoveUp, KeyCode::ArrowUp),
+                (Action::MoveDown, KeyCode::ArrowDown),
+                (Action::Restart, KeyCode::Enter),
+                (Action::Pause, KeyCode::Escape),
+            ],
+            breakout: vec![
+                (Action::MoveUp, KeyCode::ArrowRight),
+                (Action::MoveUp, KeyCode::KeyD),
+                (Action::MoveDown, KeyCode::ArrowLeft),
+                (Action::MoveDown, KeyCode::KeyA),
+                (Action::Restart, KeyCode::KeyR),
+                (Action::Restart, KeyCode::Enter),
+                (Action::Pause, KeyCode::Escape),
+                (Action::Pause, KeyCode::KeyP),
+                (Action::Mute, KeyCode::KeyM),
+            ],
+        }
+    }
+}
+
+impl KeyBindings {
+    /// The keys of the player's paddle in a game mode.
+    pub fn player(&self, mode: GameMode) -> &[(Action, KeyCode)] {
+        match mode {
+            GameMode::SinglePlayer
+            | GameMode::OnlineVersus
+            | GameMode::FourPlayer
+            | GameMode::Practice => &self.single_player,
+            GameMode::LocalVersus => &self.right,
+            GameMode::Breakout => &self.breakout,
+        }
+    }
+
+    /// How to restart a finished match in a game mode, naming the player's
+    /// restart keys and the gamepad's Start button: `R, Enter or Start`.
+    pub fn restart_prompt(&self, mode: GameMode) -> String {
+        let mut names: Vec<String> = self
+            .player(mode)
+            .iter()
+            .filter(|&&(action, _)| action == Action::Restart)
+            .map(|&(_, key)| key_name(key))
+            .collect();
+        names.push("Start".into());
+        let last = names.pop().unwrap_or_default();
+        if names.is_empty() {
+            last
+        } else {
+            format!("{} or {last}", names.join(", "))
+        }
+    }
+}
+
+/// A key as it is labelled on the keyboard, such as `R` rather than `KeyR`.
+fn key_name(key: KeyCode) -> String {
+    let name = format!("{key:?}");
+    name.strip_prefix("Key")
+        .or_else(|| name.strip_prefix("Digit"))
+        .unwrap_or(&name)
+        .to_string()
 }
 
-/// Creates an input manager bundle with keyboard controls for paddle movement.
-/// Maps the up and down arrow keys and W/S keys to the corresponding actions.
-/// Returns an `InputManagerBundle` configured with these mappings.
-pub fn setup_controls() -> InputManagerBundle<Action> {
-    InputManagerBundle::with_map(InputMap::new([
-        (Action::MoveUp, KeyCode::ArrowUp),
-        (Action::MoveUp, KeyCode::KeyW),
-        (Action::MoveDown, KeyCode::ArrowDown),
-        (Action::MoveDown, KeyCode::KeyS),
-    ]))
+/// Creates an input manager bundle mapping the given keys, and a gamepad, to
+/// their actions.
+pub fn controls(keys: &[(Action, KeyCode)]) -> InputManagerBundle<Action> {
+    InputManagerBundle::with_map(with_gamepad(InputMap::new(keys.iter().copied())))
 }
 
-/// Handles player input by updating the velocity based on movement actions.
-/// When the player presses the up or down keys (or W/S), updates the velocity direction accordingly.
-/// Takes a query for the player's velocity component and action state.
-pub fn handle(mut player: Query<(&mut Velocity, &ActionState<Action>), With<Player>>) {
-    if let Ok((mut velocity, action)) = player.get_single_mut() {
-        if action.pressed(&Action::MoveUp) {
-            velocity.direction.y = 1.;
-            return;
+/// Adds the d-pad and left stick for movement, and Start for restarting a
+/// finished match or pausing one in progress.
+fn with_gamepad(input_map: InputMap<Action>) -> InputMap<Action> {
+    input_map
+        .with(Action::MoveUp, GamepadButton::DPadUp)
+        .with(Action::MoveUp, GamepadControlDirection::LEFT_UP)
+        .with(Action::MoveDown, GamepadButton::DPadDown)
+        .with(Action::MoveDown, GamepadControlDirection::LEFT_DOWN)
+        .with(Action::Restart, GamepadButton::Start)
+        .with(Action::Pause, GamepadButton::Start)
+}
+
+/// Hands out input maps whenever the game mode or the key bindings change.
+/// The player's paddle is always driven by input; the opponent's only in local
+/// versus, otherwise its input is removed so the AI or the other player online
+/// takes over.
+pub fn apply_mode(
+    mut commands: Commands,
+    mode: Res<GameMode>,
+    settings: Res<Settings>,
+    player: Query<Entity, With<Player>>,
+    opponent: Query<Entity, With<Opponent>>,
+) {
+    if !(mode.is_changed() || settings.is_changed()) {
+        return;
+    }
+    let bindings = &settings.bindings;
+
+    for entity in &player {
+        commands
+            .entity(entity)
+            .insert(controls(bindings.player(*mode)));
+    }
+
+    for entity in &opponent {
+        match *mode {
+            GameMode::SinglePlayer
+            | GameMode::OnlineVersus
+            | GameMode::FourPlayer
+            | GameMode::Breakout
+            | GameMode::Practice => {
+                commands
+                    .entity(entity)
+                    .remove::<InputManagerBundle<Action>>();
+            }
+            GameMode::LocalVersus => {
+                commands.entity(entity).insert(controls(&bindings.left));
+            }
         }
+    }
+}
+
+/// Gives each paddle driven by input its own gamepad. In single player the
+/// player uses the first connected gamepad; in local versus the left paddle
+/// gets the first and the right paddle the second.
+pub fn assign_gamepads(
+    mode: Res<GameMode>,
+    gamepads: Query<Entity, With<Gamepad>>,
+    mut player: Query<&mut InputMap<Action>, With<Player>>,
+    mut opponent: Query<&mut InputMap<Action>, (With<Opponent>, Without<Player>)>,
+) {
+    let mut gamepads: Vec<Entity> = gamepads.iter().collect();
+    gamepads.sort();
+
+    let (left, right) = match *mode {
+        GameMode::SinglePlayer
+        | GameMode::OnlineVersus
+        | GameMode::FourPlayer
+        | GameMode::Breakout
+        | GameMode::Practice => (None, gamepads.first().copied()),
+        // Without a gamepad of its own, a paddle would fall back to whichever
+        // gamepad is found first and be moved by the other player's, so it is
+        // pointed at an entity that is never a gamepad instead.
+        GameMode::LocalVersus => (
+            Some(gamepads.first().copied().unwrap_or(Entity::PLACEHOLDER)),
+            Some(gamepads.get(1).copied().unwrap_or(Entity::PLACEHOLDER)),
+        ),
+    };
 
-        if action.pressed(&Action::MoveDown) {
-            velocity.direction.y = -1.;
-            return;
+    for (mut input_map, gamepad) in player
+        .iter_mut()
+        .map(|input_map| (input_map, right))
+        .chain(opponent.iter_mut().map(|input_map| (input_map, left)))
+    {
+        if input_map.gamepad() == gamepad {
+            continue;
         }
+        match gamepad {
+            Some(gamepad) => input_map.set_gamepad(gamepad),
+            None => input_map.clear_gamepad(),
+        };
+    }
+}
+
+/// Where a touch or the mouse wants the player's paddle, in world units along
+/// its edge, while it is steering it.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq)]
+pub struct PointerTarget(pub Option<f32>);
+
+/// The keyboard, seen through the keys that move the player's paddle.
+#[derive(SystemParam)]
+pub struct MovementKeys<'w> {
+    keys: Res<'w, ButtonInput<KeyCode>>,
+    settings: Res<'w, Settings>,
+    mode: Res<'w, GameMode>,
+}
+
+impl MovementKeys<'_> {
+    /// Whether a key moving the player's paddle was pressed this frame.
+    pub fn any_just_pressed(&self) -> bool {
+        self.settings
+            .bindings
+            .player(*self.mode)
+            .iter()
+            .any(|&(action, key)| {
+                matches!(action, Action::MoveUp | Action::MoveDown) && self.keys.just_pressed(key)
+            })
+    }
+}
+
+/// Points the player's paddle at a finger on the screen or at the mouse
+/// cursor, seen through the camera. Lifting the finger, moving the mouse out
+/// of the window or pressing one of the player's movement keys lets go of it.
+pub fn track_pointer(
+    touches: Res<Touches>,
+    movement_keys: MovementKeys,
+    mut moved: EventReader<CursorMoved>,
+    mut left: EventReader<CursorLeft>,
+    camera: Query<(&Camera, &GlobalTransform), With<Camera2d>>,
+    mut player: Query<(&mut PointerTarget, &Edge), With<Player>>,
+) {
+    let Ok((camera, camera_transform)) = camera.single() else {
+        return;
+    };
+    let to_world = |position: Vec2| camera.viewport_to_world_2d(camera_transform, position).ok();
+
+    let mut target = None;
+    let mut released = false;
+    if let Some(touch) = touches.iter().next() {
+        target = to_world(touch.position());
+    } else if touches.any_just_released() || touches.any_just_canceled() {
+        released = true;
+    }
+    if let Some(cursor) = moved.read().last() {
+        target = target.or_else(|| to_world(cursor.position));
+    }
+    if left.read().next().is_some() || movement_keys.any_just_pressed() {
+        released = true;
+    }
+
+    for (mut pointer, edge) in &mut player {
+        if let Some(target) = target {
+            pointer.0 = Some(target.dot(edge.axis()));
+        } else if released {
+            pointer.0 = None;
+        }
+    }
+}
+
+/// Holds the player's movement keys towards the pointer for this tick, while a
+/// pointer is steering the paddle.
+pub fn follow_pointer(
+    mut player: Query<(&Position, &Edge, &PointerTarget, &mut ActionState<Action>), With<Player>>,
+    tuning: Res<PaddleTuning>,
+    time: Res<Time<Fixed>>,
+) {
+    let dead_zone = pointer_dead_zone(tuning.paddle_speed, time.timestep());
+    for (position, edge, pointer, mut action) in &mut player {
+        if let Some(target) = pointer.0 {
+            hold(
+                &mut action,
+                toward(target, position.coords.dot(edge.axis()), dead_zone),
+            );
+        }
+    }
+}
+
+/// How close to the pointer, in units, a paddle moving at `speed` units per
+/// second stops when it moves once every `timestep`. A little over half of
+/// what it moves in a step, so it settles instead of stepping back and forth
+/// across the pointer.
+pub fn pointer_dead_zone(speed: f32, timestep: Duration) -> f32 {
+    0.6 * speed * timestep.as_secs_f32()
+}
+
+/// Movement that brings a paddle at `y` along its edge closer to `target`,
+/// packed into a byte, or none once it is within `dead_zone`.
+pub fn toward(target: f32, y: f32, dead_zone: f32) -> u8 {
+    let offset = target - y;
+    if offset > dead_zone {
+        MOVE_UP
+    } else if offset < -dead_zone {
+        MOVE_DOWN
+    } else {
+        0
+    }
+}
+
+/// Handles input by updating each paddle's velocity based on movement actions.
+/// When the up or down action is pressed for a paddle, updates that paddle's
+/// velocity direction along its edge accordingly. Paddles without input are
+/// left alone.
+pub fn handle(mut paddles: Query<(&mut Velocity, &Edge, &ActionState<Action>), With<Paddle>>) {
+    for (mut velocity, edge, action) in &mut paddles {
+        velocity.direction = edge.axis() * direction(pack(action));
+    }
+}
+
+/// Packs the movement held on a paddle into a byte of `MOVE_UP` and
+/// `MOVE_DOWN` bits.
+pub fn pack(action: &ActionState<Action>) -> u8 {
+    let mut held = 0;
+    if action.pressed(&Action::MoveUp) {
+        held |= MOVE_UP;
+    }
+    if action.pressed(&Action::MoveDown) {
+        held |= MOVE_DOWN;
+    }
+    held
+}
+
+/// Presses or releases the movement actions to match a packed byte.
+pub fn hold(action: &mut ActionState<Action>, held: u8) {
+    for (bit, movement) in [(MOVE_UP, Action::MoveUp), (MOVE_DOWN, Action::MoveDown)] {
+        if held & bit != 0 {
+            action.press(&movement);
+        } else {
+            action.release(&movement);
+        }
+    }
+}
+
+/// Direction along its edge a packed byte moves a paddle in, where up is
+/// positive. Up wins when both are held.
+pub fn direction(held: u8) -> f32 {
+    if held & MOVE_UP != 0 {
+        1.
+    } else if held & MOVE_DOWN != 0 {
+        -1.
+    } else {
+        0.
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paddle_moves_towards_the_pointer_until_the_dead_zone() {
+        let dead_zone = 6.;
+        assert_eq!(toward(100., 0., dead_zone), MOVE_UP);
+        assert_eq!(toward(-100., 0., dead_zone), MOVE_DOWN);
+        assert_eq!(toward(dead_zone / 2., 0., dead_zone), 0);
+        assert_eq!(toward(-dead_zone, 0., dead_zone), 0);
+    }
+
+    #[test]
+    fn dead_zone_is_a_little_over_half_a_step() {
+        // 240 units per second at 64 ticks per second is 3.75 units a tick
+        let dead_zone = pointer_dead_zone(240., Duration::from_secs_f64(1. / 64.));
+        assert!(dead_zone > 3.75 / 2. && dead_zone < 3.75);
+    }
+
+    #[test]
+    fn restart_prompt_follows_the_bindings() {
+        let mut bindings = KeyBindings::default();
+        assert_eq!(
+            bindings.restart_prompt(GameMode::SinglePlayer),
+            "R, Enter or Start"
+        );
 
-        velocity.direction.y = 0.0;
+        bindings
+            .single_player
+            .retain(|&(action, _)| action != Action::Restart);
+        bindings
+            .single_player
+            .push((Action::Restart, KeyCode::Digit1));
+        assert_eq!(
+            bindings.restart_prompt(GameMode::SinglePlayer),
+            "1 or Start"
+        );
     }
 }
@@ -0,0 +1,562 @@
+//! Obstacles in the arena, laid out per match.
+//!
+//! A `Layout` names a set of obstacles, each spawned as a `Position` and
+//! `Shape` like the paddles and gutters, so the ball bounces off them without
+//! anything else to handle in `physics`:
+//! - `Bumper`s stay where they are put
+//! - `Sliding` bumpers move back and forth between two points
+//! - A `Wall` runs across the arena with gaps the ball can pass through
+//! - `Rotating` blockers turn a quarter turn at a time, between lying across
+//!   the arena and along it; colliders are boxes, so they turn in steps
+//!   rather than smoothly
+//!
+//! When drawn to a window, the game loads the layouts to pick from out of
+//! `assets/layouts.ron`, next to the open arena which is always there. Each
+//! one is validated when the file is loaded: its obstacles have to stay in the
+//! arena, out of the left and right paddles' lanes and off the spot the ball
+//! is served from, at every point of their motion. Layouts that are not are
+//! left out with a warning. Four player matches check the top and bottom
+//! lanes too when the match starts, and play in the open arena if a layout
+//! gets in their way. Online matches and breakout are always played in the
+//! open arena.
+//!
+//! A headless game plays in whatever `Layout` resource it is given, the open
+//! arena unless a test inserts another.
+
+use std::{f32::consts::TAU, fmt, time::Duration};
+
+use bevy::{
+    asset::{Asset, Assets},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::{Added, With},
+        resource::Resource,
+        system::{Commands, Query, Res, ResMut},
+    },
+    log::{info, warn},
+    math::{primitives::Rectangle, Rect, Vec2, Vec2Swizzles},
+    reflect::TypePath,
+    render::mesh::{Mesh, Mesh2d},
+    sprite::{ColorMaterial, MeshMaterial2d},
+    time::{Time, Timer, TimerMode},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    accessibility::ArenaColors,
+    arena::Arena,
+    components::{Edge, Position, Shape},
+    game::GameMode,
+    replay::Playback,
+    ron_asset::{Reloaded, RonAsset},
+    settings::Settings,
+    tuning::PaddleTuning,
+};
+
+/// Where the layouts are loaded from, in the assets folder.
+pub const PATH: &str = "layouts.ron";
+
+/// Name of the arena without any obstacles.
+pub const OPEN: &str = "Open";
+
+/// The obstacles a match is played with.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Layout {
+    pub name: String,
+    #[serde(default)]
+    pub obstacles: Vec<ObstacleKind>,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self {
+            name: OPEN.into(),
+            obstacles: Vec::new(),
+        }
+    }
+}
+
+/// An obstacle as it is described in a layout. Positions are from the center
+/// of the arena, and sizes and periods in units and seconds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ObstacleKind {
+    Bumper {
+        center: Vec2,
+        size: Vec2,
+    },
+    /// Moves from `from` to `to` and back every `period`.
+    Sliding {
+        from: Vec2,
+        to: Vec2,
+        size: Vec2,
+        period: f32,
+    },
+    /// Runs from the bottom of the arena to the top at `x`, but for its gaps.
+    Wall {
+        x: f32,
+        thickness: f32,
+        gaps: Vec<Gap>,
+    },
+    /// Lies across the arena at first, and turns a quarter turn every
+    /// `interval`.
+    Rotating {
+        center: Vec2,
+        length: f32,
+        thickness: f32,
+        interval: f32,
+    },
+}
+
+/// An opening in a `Wall`, `size` high around `center`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Gap {
+    pub center: f32,
+    pub size: f32,
+}
+
+impl ObstacleKind {
+    /// The boxes this obstacle is made of where it starts.
+    fn boxes(&self, arena: &Arena) -> Vec<Rect> {
+        match *self {
+            Self::Bumper { center, size } => vec![Rect::from_center_size(center, size)],
+            Self::Sliding { from, size, .. } => vec![Rect::from_center_size(from, size)],
+            Self::Wall {
+                x,
+                thickness,
+                ref gaps,
+            } => wall_segments(x, thickness, gaps, arena),
+            Self::Rotating {
+                center,
+                length,
+                thickness,
+                ..
+            } => vec![Rect::from_center_size(center, Vec2::new(length, thickness))],
+        }
+    }
+
+    /// Everywhere this obstacle can be at some point of its motion.
+    fn reach(&self, arena: &Arena) -> Vec<Rect> {
+        match *self {
+            Self::Sliding { from, to, size, .. } => {
+                vec![Rect::from_center_size(from, size).union(Rect::from_center_size(to, size))]
+            }
+            Self::Rotating {
+                center,
+                length,
+                thickness,
+                ..
+            } => vec![
+                Rect::from_center_size(center, Vec2::new(length, thickness)),
+                Rect::from_center_size(center, Vec2::new(thickness, length)),
+            ],
+            _ => self.boxes(arena),
+        }
+    }
+
+    fn period(&self) -> Option<f32> {
+        match *self {
+            Self::Sliding { period, .. } => Some(period),
+            Self::Rotating { interval, .. } => Some(interval),
+            Self::Bumper { .. } | Self::Wall { .. } => None,
+        }
+    }
+}
+
+/// The parts of a wall left between its gaps, from the bottom of the arena up.
+fn wall_segments(x: f32, thickness: f32, gaps: &[Gap], arena: &Arena) -> Vec<Rect> {
+    let mut gaps = gaps.to_vec();
+    gaps.sort_by(|a, b| a.center.total_cmp(&b.center));
+
+    let mut segments = Vec::new();
+    let mut bottom = -arena.half_height();
+    for gap in gaps {
+        let top = gap.center - gap.size / 2.;
+        if top > bottom {
+            segments.push(Rect::new(
+                x - thickness / 2.,
+                bottom,
+                x + thickness / 2.,
+                top,
+            ));
+        }
+        bottom = bottom.max(gap.center + gap.size / 2.);
+    }
+    if bottom < arena.half_height() {
+        segments.push(Rect::new(
+            x - thickness / 2.,
+            bottom,
+            x + thickness / 2.,
+            arena.half_height(),
+        ));
+    }
+    segments
+}
+
+/// Why a layout cannot be played, naming the obstacle at fault by its index.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LayoutError {
+    OutOfArena(usize),
+    InLane(usize, Edge),
+    OnServe(usize),
+    NotMoving(usize),
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfArena(index) => write!(f, "obstacle {index} leaves the arena"),
+            Self::InLane(index, edge) => {
+                write!(f, "obstacle {index} is in the way of the {edge:?} paddle")
+            }
+            Self::OnServe(index) => write!(f, "obstacle {index} covers the serve"),
+            Self::NotMoving(index) => write!(f, "obstacle {index} needs a positive period"),
+        }
+    }
+}
+
+impl Layout {
+    /// Checks that every obstacle stays in the arena, out of the lanes the
+    /// paddles on `edges` move in and off the spot the ball is served from.
+    /// Lanes are as wide as a paddle with room for the ball on either side.
+    pub fn validate(
+        &self,
+        arena: &Arena,
+        tuning: &PaddleTuning,
+        edges: &[Edge],
+    ) -> Result<(), LayoutError> {
+        let bounds = Rect::from_center_size(Vec2::ZERO, arena.size);
+        let ball = tuning.ball_size * 2.;
+        let serve = Rect::from_center_size(Vec2::ZERO, Vec2::splat(ball));
+        let lane = |edge: Edge| {
+            Rect::from_center_size(
+                edge.paddle_position(arena),
+                edge.outward().abs() * (tuning.paddle_width + ball * 2.)
+                    + edge.axis() * edge.half_length(arena) * 2.,
+            )
+        };
+        let overlaps = |a: Rect, b: Rect| !a.intersect(b).is_empty();
+
+        for (index, obstacle) in self.obstacles.iter().enumerate() {
+            if obstacle.period().is_some_and(|period| period <= 0.) {
+                return Err(LayoutError::NotMoving(index));
+            }
+            for area in obstacle.reach(arena) {
+                if bounds.union(area) != bounds {
+                    return Err(LayoutError::OutOfArena(index));
+                }
+                if overlaps(area, serve) {
+                    return Err(LayoutError::OnServe(index));
+                }
+                if let Some(&edge) = edges.iter().find(|&&edge| overlaps(area, lane(edge))) {
+                    return Err(LayoutError::InLane(index, edge));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Every layout that can be picked, the open arena first.
+#[derive(Asset, Resource, TypePath, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Layouts(pub Vec<Layout>);
+
+impl Default for Layouts {
+    fn default() -> Self {
+        Self(vec![Layout::default()])
+    }
+}
+
+impl Layouts {
+    pub fn get(&self, name: &str) -> Option<&Layout> {
+        self.0.iter().find(|layout| layout.name == name)
+    }
+
+    /// The layout picked after `name`, back to the first after the last.
+    pub fn after(&self, name: &str) -> &Layout {
+        let next = self
+            .0
+            .iter()
+            .position(|layout| layout.name == name)
+            .map_or(0, |index| index + 1);
+        &self.0[next % self.0.len()]
+    }
+}
+
+impl RonAsset for Layouts {
+    const PATH: &'static str = PATH;
+}
+
+/// Validates the layouts and offers the ones that pass whenever the file is
+/// loaded or changes.
+pub fn apply(
+    mut file: Reloaded<Layouts>,
+    mut layouts: ResMut<Layouts>,
+    arena: Res<Arena>,
+    tuning: Res<PaddleTuning>,
+) {
+    let Some(loaded) = file.get() else {
+        return;
+    };
+
+    let mut valid = Layouts::default();
+    for layout in &loaded.0 {
+        match layout.validate(&arena, &tuning, GameMode::SinglePlayer.edges()) {
+            Ok(()) => valid.0.push(layout.clone()),
+            Err(error) => warn!("Left out the {} layout: {error}", layout.name),
+        }
+    }
+    info!("Loaded {} layouts from {PATH}", valid.0.len() - 1);
+    *layouts = valid;
+}
+
+#[derive(Component)]
+#[require(Position, Shape)]
+pub struct Obstacle;
+
+/// Moves a sliding bumper back and forth, easing in and out at either end.
+#[derive(Component, Debug, Clone)]
+pub struct Slide {
+    from: Vec2,
+    to: Vec2,
+    period: f32,
+    elapsed: f32,
+}
+
+/// Turns a rotating blocker a quarter turn every time it finishes.
+#[derive(Component, Debug, Clone)]
+pub struct Turn(Timer);
+
+/// Picks the layout in the settings for the next match, unless a replay is
+/// played back in the layout it was recorded in. Games without layouts to
+/// pick from, such as headless ones, keep the one they were given.
+pub fn choose(
+    mut layout: ResMut<Layout>,
+    layouts: Option<Res<Layouts>>,
+    settings: Res<Settings>,
+    playback: Option<Res<Playback>>,
+) {
+    let Some(layouts) = layouts else {
+        return;
+    };
+    if playback.is_some() {
+        return;
+    }
+    *layout = layouts.get(&settings.layout).cloned().unwrap_or_default();
+}
+
+/// Replaces the obstacles of the last match with the layout's.
+pub fn spawn_obstacles(
+    mut commands: Commands,
+    layout: Res<Layout>,
+    mode: Res<GameMode>,
+    arena: Res<Arena>,
+    tuning: Res<PaddleTuning>,
+    obstacles: Query<Entity, With<Obstacle>>,
+) {
+    for entity in &obstacles {
+        commands.entity(entity).despawn();
+    }
+
+    // Breakout fills the arena with bricks instead
+    if matches!(*mode, GameMode::OnlineVersus | GameMode::Breakout) {
+        return;
+    }
+    if let Err(error) = layout.validate(&arena, &tuning, mode.edges()) {
+        warn!(
+            "Playing in the open arena, the {} layout: {error}",
+            layout.name
+        );
+        return;
+    }
+
+    for obstacle in &layout.obstacles {
+        for area in obstacle.boxes(&arena) {
+            let mut entity = commands.spawn((
+                Obstacle,
+                Position {
+                    coords: area.center(),
+                },
+                Shape { size: area.size() },
+            ));
+            match *obstacle {
+                ObstacleKind::Sliding {
+                    from, to, period, ..
+                } => {
+                    entity.insert(Slide {
+                        from,
+                        to,
+                        period,
+                        elapsed: 0.,
+                    });
+                }
+                ObstacleKind::Rotating { interval, .. } => {
+                    entity.insert(Turn(Timer::new(
+                        Duration::from_secs_f32(interval),
+                        TimerMode::Repeating,
+                    )));
+                }
+                ObstacleKind::Bumper { .. } | ObstacleKind::Wall { .. } => {}
+            }
+        }
+    }
+}
+
+pub fn move_obstacles(
+    mut sliding: Query<(&mut Position, &mut Slide)>,
+    mut turning: Query<(&mut Shape, &mut Turn)>,
+    time: Res<Time>,
+) {
+    for (mut position, mut slide) in &mut sliding {
+        slide.elapsed = (slide.elapsed + time.delta_secs()) % slide.period;
+        let progress = (1. - (slide.elapsed / slide.period * TAU).cos()) / 2.;
+        position.coords = slide.from.lerp(slide.to, progress);
+    }
+    for (mut shape, mut turn) in &mut turning {
+        if turn.0.tick(time.delta()).just_finished() {
+            shape.size = shape.size.yx();
+        }
+    }
+}
+
+/// Attaches a mesh and material to every newly spawned obstacle.
+pub fn add_meshes(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    colors: ArenaColors,
+    obstacles: Query<Entity, Added<Obstacle>>,
+) {
+    let color = colors.get().obstacle;
+    for entity in &obstacles {
+        let mesh = meshes.add(Rectangle::new(1., 1.));
+        let material = materials.add(color);
+        commands
+            .entity(entity)
+            .insert((Mesh2d(mesh), MeshMaterial2d(material)));
+    }
+}
+
+/// Repaints every obstacle whenever the tuning or the palette changes.
+pub fn recolor(
+    colors: ArenaColors,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    obstacles: Query<&MeshMaterial2d<ColorMaterial>, With<Obstacle>>,
+) {
+    if !colors.is_changed() {
+        return;
+    }
+
+    let color = colors.get().obstacle;
+    for material in &obstacles {
+        if let Some(material) = materials.get_mut(material) {
+            material.color = color;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(obstacles: Vec<ObstacleKind>) -> Layout {
+        Layout {
+            name: "Test".into(),
+            obstacles,
+        }
+    }
+
+    fn validate(layout: &Layout, edges: &[Edge]) -> Result<(), LayoutError> {
+        layout.validate(&Arena::default(), &PaddleTuning::default(), edges)
+    }
+
+    #[test]
+    fn shipped_layouts_are_valid() {
+        let layouts: Layouts = ron::from_str(include_str!("../assets/layouts.ron")).unwrap();
+
+        assert!(!layouts.0.is_empty());
+        for layout in &layouts.0 {
+            assert_eq!(
+                validate(layout, GameMode::SinglePlayer.edges()),
+                Ok(()),
+                "{}",
+                layout.name
+            );
+        }
+    }
+
+    #[test]
+    fn obstacles_sliding_into_a_lane_are_rejected() {
+        let right = Edge::Right.paddle_position(&Arena::default());
+        let sliding = layout(vec![ObstacleKind::Sliding {
+            from: Vec2::new(200., 100.),
+            to: right.with_y(100.),
+            size: Vec2::splat(20.),
+            period: 2.,
+        }]);
+
+        assert_eq!(
+            validate(&sliding, &Edge::ALL),
+            Err(LayoutError::InLane(0, Edge::Right))
+        );
+    }
+
+    #[test]
+    fn blockers_are_checked_in_both_orientations() {
+        let rotating = layout(vec![ObstacleKind::Rotating {
+            center: Vec2::new(200., 250.),
+            length: 200.,
+            thickness: 10.,
+            interval: 1.,
+        }]);
+
+        assert_eq!(validate(&rotating, GameMode::SinglePlayer.edges()), Ok(()));
+        // Turned along the arena it reaches into the top paddle's lane
+        assert_eq!(
+            validate(&rotating, &Edge::ALL),
+            Err(LayoutError::InLane(0, Edge::Top))
+        );
+    }
+
+    #[test]
+    fn walls_have_to_leave_the_serve_open() {
+        let wall = |gap: f32| {
+            layout(vec![ObstacleKind::Wall {
+                x: 0.,
+                thickness: 10.,
+                gaps: vec![Gap {
+                    center: 0.,
+                    size: gap,
+                }],
+            }])
+        };
+
+        assert_eq!(validate(&wall(100.), &Edge::ALL[..2]), Ok(()));
+        assert_eq!(
+            validate(&wall(0.), &Edge::ALL[..2]),
+            Err(LayoutError::OnServe(0))
+        );
+    }
+
+    #[test]
+    fn walls_are_split_around_their_gaps() {
+        let gaps = [
+            Gap {
+                center: 100.,
+                size: 40.,
+            },
+            Gap {
+                center: -100.,
+                size: 40.,
+            },
+        ];
+        let segments = wall_segments(0., 10., &gaps, &Arena::default());
+
+        let spans: Vec<(f32, f32)> = segments
+            .iter()
+            .map(|rect| (rect.min.y, rect.max.y))
+            .collect();
+        assert_eq!(spans, [(-360., -120.), (-80., 80.), (120., 360.)]);
+    }
+}
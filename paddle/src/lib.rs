@@ -0,0 +1,517 @@
+//! A pong-inspired game built with Bevy.
+//!
+//! The whole game is packaged as the `PaddlePlugin`. The binary adds it next to
+//! `DefaultPlugins` to play in a window, while tests and bots can add it next to
+//! `MinimalPlugins` with `headless` set to run the same simulation without any
+//! rendering, which `env::PaddleEnv` does to step it for training agents.
+//!
+//! The simulation is stepped in `FixedUpdate` so it plays the same regardless of
+//! frame rate; only drawing happens every frame. It only runs while the app is
+//! in `AppState::Playing`, so the menus and the pause overlay hold it still.
+//! Online matches step the same systems in GGRS' `GgrsSchedule` instead, see
+//! the `session` module.
+
+use bevy::{
+    ecs::{schedule::ScheduleConfigs, system::ScheduleSystem},
+    prelude::*,
+    state::app::StatesPlugin,
+};
+use bevy_ggrs::{GgrsPlugin, GgrsSchedule, ReadInputs, RollbackApp};
+use leafwing_input_manager::plugin::InputManagerPlugin;
+
+use ai::Difficulty;
+use arena::Arena;
+use components::{
+    add_meshes, move_player_paddle, spawn_gutters, spawn_paddles, Ball, Position, PreviousPosition,
+    Speed, Velocity,
+};
+use game::{
+    detect_scoring, end_rally, next_serve, reset_ball, restart, resume_after_point, serve,
+    start_match, start_point_pause, start_serve_delay, toggle_pause, update_score, AppState,
+    Countdown, GameMode, MatchState, Score, Serve,
+};
+use menu::MenuScreen;
+use replay::{Playback, Recording, Replay};
+use ron_asset::RonLoader;
+use settings::{MatchHistory, Settings};
+
+pub mod accessibility;
+pub mod ai;
+pub mod arena;
+pub mod audio;
+pub mod breakout;
+pub mod components;
+pub mod effects;
+pub mod env;
+pub mod game;
+pub mod input;
+pub mod layout;
+pub mod menu;
+pub mod physics;
+pub mod powerup;
+pub mod practice;
+pub mod replay;
+pub mod ron_asset;
+pub mod scoreboard;
+pub mod session;
+pub mod settings;
+pub mod stats;
+pub mod storage;
+pub mod tuning;
+
+#[derive(Default)]
+pub struct PaddlePlugin {
+    /// Size of the playing field.
+    pub arena: Arena,
+    /// Whether the opponent is played by the AI, by a second local player or
+    /// by someone online, whether three AI paddles guard the other edges, or
+    /// whether the player breaks bricks on their own.
+    pub mode: GameMode,
+    /// How good the AI opponent is, unless the player saved another difficulty
+    /// in their settings.
+    pub difficulty: Difficulty,
+    /// Skips the camera, meshes, scoreboard, menus, input devices and saved
+    /// settings so the game can run under `MinimalPlugins`. A headless game
+    /// starts playing right away instead of waiting in the main menu.
+    pub headless: bool,
+    /// A recorded match to play back instead of the arena, mode, difficulty and
+    /// rules above. Playback starts right away, like a headless game.
+    pub replay: Option<Replay>,
+}
+
+impl PaddlePlugin {
+    pub fn headless(arena: Arena) -> Self {
+        Self {
+            arena,
+            headless: true,
+            ..Default::default()
+        }
+    }
+}
+
+impl Plugin for PaddlePlugin {
+    fn build(&self, app: &mut App) {
+        // `MinimalPlugins` does not come with states
+        if !app.is_plugin_added::<StatesPlugin>() {
+            app.add_plugins(StatesPlugin);
+        }
+
+        let defaults = Settings {
+            difficulty: self.difficulty,
+            ..Default::default()
+        };
+        let (settings, history) = if self.headless {
+            (defaults, MatchHistory::default())
+        } else {
+            (
+                Settings::load().unwrap_or(defaults),
+                MatchHistory::load().unwrap_or_default(),
+            )
+        };
+
+        let (arena, mode, difficulty, rules) = match &self.replay {
+            Some(replay) => (replay.arena, replay.mode, replay.difficulty, replay.rules),
+            None => (
+                self.arena,
+                self.mode,
+                settings.difficulty,
+                game::MatchRules {
+                    points_to_win: settings.points_to_win,
+                    power_ups: settings.power_ups,
+                    ball_speed: settings::ball_speed(&settings, self.mode),
+                    ..Default::default()
+                },
+            ),
+        };
+        // A headless online game skips matchmaking too; it is handed a session,
+        // such as a sync test, rather than looking for an opponent
+        let initial_state = if self.headless || self.replay.is_some() {
+            AppState::Playing
+        } else {
+            AppState::MainMenu
+        };
+
+        if let Some(replay) = &self.replay {
+            app.insert_resource(Playback::new(replay.clone()));
+        }
+        let layout = self
+            .replay
+            .as_ref()
+            .map(|replay| replay.layout.clone())
+            .unwrap_or_default();
+        let drill = self
+            .replay
+            .as_ref()
+            .and_then(|replay| replay.drill.clone())
+            .unwrap_or_default();
+        let tuning = self
+            .replay
+            .as_ref()
+            .map(|replay| replay.tuning.clone())
+            .unwrap_or_default();
+        let levels = self
+            .replay
+            .as_ref()
+            .and_then(|replay| replay.levels.clone())
+            .unwrap_or_default();
+
+        let effects = if self.headless {
+            effects::EffectsSettings::NONE
+        } else {
+            settings.effects
+        };
+
+        app.insert_resource(arena)
+            .insert_resource(mode)
+            .insert_resource(difficulty)
+            .insert_resource(rules)
+            .insert_resource(layout)
+            .insert_resource(drill)
+            .insert_resource(effects)
+            .insert_resource(settings.accessibility)
+            .insert_resource(tuning)
+            .insert_resource(settings)
+            .insert_resource(history)
+            .init_resource::<game::GameRng>()
+            .init_resource::<game::Score>()
+            .init_resource::<game::Serve>()
+            .init_resource::<game::Countdown>()
+            .init_resource::<powerup::PowerUpSpawner>()
+            .init_resource::<Recording>()
+            .init_resource::<stats::MatchStats>()
+            .insert_resource(levels)
+            .init_resource::<breakout::Lives>()
+            .init_resource::<breakout::CurrentLevel>()
+            .init_resource::<practice::DrillProgress>()
+            .insert_state(initial_state)
+            .add_sub_state::<MatchState>()
+            .add_event::<game::PointScored>()
+            .add_event::<game::RallyEnded>()
+            .add_event::<game::MatchWon>()
+            .add_event::<physics::BallCollided>()
+            .add_systems(Startup, (Ball::spawn, spawn_paddles, spawn_gutters))
+            .add_systems(
+                OnEnter(MatchState::Serving),
+                (
+                    start_serve_delay,
+                    practice::time_shot
+                        .after(start_serve_delay)
+                        .run_if(practice::enabled),
+                ),
+            )
+            .add_systems(OnEnter(MatchState::PointPause), start_point_pause)
+            .add_systems(OnExit(AppState::MainMenu), start_new_match())
+            .add_systems(OnExit(AppState::GameOver), start_new_match())
+            .add_systems(OnEnter(AppState::MainMenu), replay::stop_playback)
+            .add_systems(OnEnter(AppState::GameOver), replay::stop_playback)
+            .add_systems(
+                FixedFirst,
+                (
+                    game::apply_match_state_transition,
+                    Position::remember,
+                    components::apply_tuning,
+                ),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    (
+                        // ball related systems
+                        Ball::movement,
+                        physics::handle_collisions.after(Ball::movement),
+                        // game state management
+                        detect_scoring.after(physics::handle_collisions),
+                        next_serve
+                            .after(detect_scoring)
+                            .run_if(not(practice::enabled)),
+                        reset_ball.after(next_serve).after(practice::next_shot),
+                        update_score
+                            .after(detect_scoring)
+                            .run_if(not(breakout::enabled).and(not(practice::enabled))),
+                        end_rally
+                            .after(update_score)
+                            .run_if(not(breakout::enabled).and(not(practice::enabled))),
+                        // breakout keeps its own score
+                        (
+                            breakout::hit_bricks
+                                .after(physics::handle_collisions)
+                                .before(detect_scoring),
+                            breakout::end_rally
+                                .after(breakout::hit_bricks)
+                                .after(detect_scoring),
+                        )
+                            .run_if(breakout::enabled),
+                        // and so does practice
+                        (
+                            practice::count_returns
+                                .after(physics::handle_collisions)
+                                .before(detect_scoring),
+                            practice::next_shot
+                                .after(practice::count_returns)
+                                .after(detect_scoring),
+                            practice::end_rally.after(practice::next_shot),
+                        )
+                            .run_if(practice::enabled),
+                        // statistics
+                        stats::count_hits.after(physics::handle_collisions),
+                        stats::count_points
+                            .after(detect_scoring)
+                            .before(next_serve)
+                            .before(practice::next_shot),
+                        // power-ups
+                        (
+                            powerup::spawn,
+                            powerup::collect.after(physics::handle_collisions),
+                            powerup::stick.after(physics::handle_collisions),
+                            powerup::carry
+                                .after(powerup::stick)
+                                .after(move_player_paddle),
+                            powerup::release_after_rally.after(detect_scoring),
+                        )
+                            .run_if(powerup::enabled),
+                    )
+                        .run_if(in_state(MatchState::Rally)),
+                    serve.run_if(in_state(MatchState::Serving)),
+                    resume_after_point.run_if(in_state(MatchState::PointPause)),
+                    // Timed power-ups keep running down between rallies
+                    powerup::expire.run_if(powerup::enabled),
+                    layout::move_obstacles,
+                    breakout::spawn_level,
+                    // input related systems
+                    input::follow_pointer.before(replay::play),
+                    replay::play.before(input::handle),
+                    replay::record.after(replay::play).before(input::handle),
+                    input::handle,
+                    // paddle related systems
+                    move_player_paddle.after(input::handle),
+                    ai::steer
+                        .after(input::handle)
+                        .before(move_player_paddle)
+                        .run_if(ai::enabled),
+                )
+                    .run_if(in_state(AppState::Playing))
+                    .run_if(not(game::match_decided))
+                    .run_if(not(resource_equals(GameMode::OnlineVersus))),
+            )
+            // Paddles are laid out for the mode and get their controls before
+            // the fixed ticks of the same frame
+            .add_systems(
+                PreUpdate,
+                (
+                    components::lay_out,
+                    input::apply_mode.after(components::lay_out),
+                    ai::apply_difficulty.after(components::lay_out),
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    Position::project,
+                    restart.run_if(in_state(AppState::GameOver)),
+                    toggle_pause
+                        .run_if(in_state(AppState::Playing).or(in_state(AppState::Paused)))
+                        .run_if(not(resource_equals(GameMode::OnlineVersus))),
+                ),
+            );
+
+        // Online matches are stepped by GGRS, which rolls everything the rally
+        // changes back whenever a late input differs from its prediction. Only
+        // games that can go online need it: windowed ones can pick online
+        // versus from the menus, while headless ones and replays stay in the
+        // mode they were started in
+        let can_go_online =
+            self.replay.is_none() && (!self.headless || self.mode == GameMode::OnlineVersus);
+        if can_go_online {
+            app.add_plugins(GgrsPlugin::<session::Config>::default())
+                .init_resource::<session::MatchDecided>()
+                .init_resource::<session::Played<physics::BallCollided>>()
+                .init_resource::<session::Played<game::PointScored>>()
+                .rollback_component_with_copy::<Position>()
+                .rollback_component_with_copy::<PreviousPosition>()
+                .rollback_component_with_copy::<Velocity>()
+                .rollback_component_with_copy::<Speed>()
+                .rollback_resource_with_copy::<Score>()
+                .rollback_resource_with_copy::<Serve>()
+                .rollback_resource_with_copy::<session::MatchDecided>()
+                .rollback_resource_with_clone::<Countdown>()
+                .rollback_resource_with_clone::<stats::MatchStats>()
+                .checksum_component::<Position>(session::checksum_position)
+                .add_systems(
+                    PostStartup,
+                    session::setup_rollback.run_if(resource_equals(GameMode::OnlineVersus)),
+                )
+                .add_systems(
+                    OnEnter(AppState::Matchmaking),
+                    session::setup_rollback.run_if(resource_equals(GameMode::OnlineVersus)),
+                )
+                .add_systems(OnEnter(AppState::MainMenu), session::disconnect)
+                .add_systems(OnEnter(AppState::GameOver), session::disconnect)
+                .add_systems(ReadInputs, session::read_inputs)
+                .add_systems(
+                    GgrsSchedule,
+                    (
+                        session::clear_played::<physics::BallCollided>,
+                        session::clear_played::<game::PointScored>,
+                        Position::remember,
+                        session::apply_inputs,
+                        move_player_paddle,
+                        Ball::movement,
+                        physics::handle_collisions,
+                        stats::count_hits,
+                        detect_scoring,
+                        stats::count_points,
+                        next_serve,
+                        reset_ball,
+                        update_score,
+                        session::end_rally,
+                        session::serve,
+                        session::remember_played::<physics::BallCollided>,
+                        session::remember_played::<game::PointScored>,
+                    )
+                        .chain(),
+                )
+                // After PreUpdate, where GGRS steps the frames, and before
+                // Update, where their sounds and effects play
+                .add_systems(
+                    RunFixedMainLoop,
+                    (
+                        session::play::<physics::BallCollided>,
+                        session::play::<game::PointScored>,
+                    )
+                        .in_set(RunFixedMainLoopSystem::BeforeFixedMainLoop),
+                )
+                .add_systems(Update, session::finish.run_if(in_state(AppState::Playing)));
+        }
+
+        // Games that skip the main menu still need their match set up
+        if initial_state == AppState::Playing {
+            app.add_systems(PostStartup, start_new_match());
+        }
+
+        if self.headless {
+            return;
+        }
+
+        app.add_plugins(InputManagerPlugin::<input::Action>::default())
+            .init_asset::<tuning::PaddleTuning>()
+            .init_asset_loader::<RonLoader<tuning::PaddleTuning>>()
+            .init_asset::<layout::Layouts>()
+            .init_asset_loader::<RonLoader<layout::Layouts>>()
+            .init_resource::<layout::Layouts>()
+            .init_asset::<breakout::Levels>()
+            .init_asset_loader::<RonLoader<breakout::Levels>>()
+            .init_asset::<practice::Drills>()
+            .init_asset_loader::<RonLoader<practice::Drills>>()
+            .init_resource::<practice::Drills>()
+            .insert_resource(practice::DrillRecords::load().unwrap_or_default())
+            .init_resource::<effects::CameraShake>()
+            .init_resource::<effects::TrailTimer>()
+            .add_systems(
+                Startup,
+                (
+                    arena::spawn_camera,
+                    scoreboard::spawn,
+                    audio::setup,
+                    effects::setup,
+                    ron_asset::load::<tuning::PaddleTuning>,
+                    ron_asset::load::<layout::Layouts>,
+                    ron_asset::load::<breakout::Levels>,
+                    ron_asset::load::<practice::Drills>,
+                ),
+            )
+            .add_sub_state::<MenuScreen>()
+            .add_systems(OnEnter(MenuScreen::Main), menu::spawn_main_menu)
+            .add_systems(OnEnter(MenuScreen::Settings), menu::spawn_settings)
+            .add_systems(
+                OnEnter(MenuScreen::Accessibility),
+                menu::spawn_accessibility,
+            )
+            .add_systems(
+                OnEnter(AppState::Matchmaking),
+                (menu::spawn_matchmaking, session::connect),
+            )
+            .add_systems(OnEnter(AppState::Paused), menu::spawn_pause_menu)
+            .add_systems(OnEnter(AppState::GameOver), menu::spawn_game_over_menu)
+            .add_systems(OnExit(AppState::GameOver), scoreboard::clear_banner)
+            .add_systems(OnEnter(AppState::MainMenu), replay::save)
+            .add_systems(OnEnter(AppState::GameOver), replay::save)
+            .add_systems(OnEnter(AppState::GameOver), stats::export)
+            .add_systems(
+                PreUpdate,
+                (
+                    input::assign_gamepads.after(input::apply_mode),
+                    input::track_pointer,
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    add_meshes,
+                    components::scale_meshes,
+                    components::recolor,
+                    // assets picked up while the game runs
+                    (
+                        tuning::apply,
+                        layout::apply,
+                        layout::add_meshes,
+                        layout::recolor,
+                        breakout::apply,
+                        practice::apply,
+                    ),
+                    (
+                        powerup::add_meshes,
+                        powerup::recolor,
+                        breakout::add_meshes,
+                        breakout::recolor,
+                    ),
+                    (
+                        scoreboard::update,
+                        scoreboard::announce_winner,
+                        scoreboard::resize,
+                    ),
+                    menu::highlight,
+                    menu::press,
+                    menu::update_labels,
+                    settings::apply,
+                    settings::save,
+                    (settings::record_match, practice::record),
+                    audio::play_collisions,
+                    audio::play_points,
+                    audio::toggle_mute,
+                    audio::apply_volume,
+                    (
+                        effects::recolor,
+                        effects::on_collision,
+                        effects::leave_trail.run_if(in_state(AppState::Playing)),
+                        effects::fade,
+                        effects::unsquash.after(components::scale_meshes),
+                        effects::start_shake,
+                        effects::shake_camera.after(effects::start_shake),
+                    ),
+                    // Restart doubles as a shortcut for starting from the menu
+                    restart.run_if(in_state(MenuScreen::Main)),
+                    session::wait_for_players.run_if(in_state(AppState::Matchmaking)),
+                ),
+            );
+    }
+}
+
+/// Sets up a new match, whether it was started from the menus or the game
+/// started straight into it.
+fn start_new_match() -> ScheduleConfigs<ScheduleSystem> {
+    (
+        layout::choose,
+        layout::spawn_obstacles,
+        practice::choose,
+        game::first_serve,
+        practice::start,
+        start_match,
+        breakout::start,
+        game::reset_paddles,
+        ai::reset,
+        powerup::reset,
+        stats::reset,
+        replay::start_recording,
+    )
+        .chain()
+}
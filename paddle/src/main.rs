@@ -1,15 +1,40 @@
 use bevy::prelude::*;
-use components::{move_opponent_paddle, move_player_paddle, spawn_gutters, spawn_paddles, Ball, Position};
-use game::{detect_scoring, reset_ball, update_score};
-use leafwing_input_manager::plugin::InputManagerPlugin;
-
-mod components;
-mod input;
-mod physics;
-mod game;
-mod scoreboard;
+use paddle::{game::GameMode, replay::Replay, PaddlePlugin};
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+
+    // Pass `--versus` to play against a second person on the same machine,
+    // `--online` to play against someone else over the network, `--four` to
+    // guard one of four edges against three AI paddles, `--breakout` to break
+    // bricks, or `--practice` to return shots from a launcher
+    let mode = if args.iter().any(|arg| arg == "--versus") {
+        GameMode::LocalVersus
+    } else if args.iter().any(|arg| arg == "--online") {
+        GameMode::OnlineVersus
+    } else if args.iter().any(|arg| arg == "--four") {
+        GameMode::FourPlayer
+    } else if args.iter().any(|arg| arg == "--breakout") {
+        GameMode::Breakout
+    } else if args.iter().any(|arg| arg == "--practice") {
+        GameMode::Practice
+    } else {
+        GameMode::SinglePlayer
+    };
+
+    // Pass `--replay` to watch the last match again, or `--replay <file>` to
+    // watch a saved one
+    let replay = args.iter().position(|arg| arg == "--replay").map(|index| {
+        let path = args
+            .get(index + 1)
+            .map(String::as_str)
+            .filter(|path| !path.starts_with("--"));
+        read_replay(path).unwrap_or_else(|error| {
+            eprintln!("{error}");
+            std::process::exit(1);
+        })
+    });
+
     App::new()
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
@@ -20,44 +45,20 @@ fn main() {
             }),
             ..Default::default()
         }))
-        .add_plugins(InputManagerPlugin::<input::Action>::default())
-        .init_resource::<game::Score>()
-        .add_event::<game::PointScored>()
-        .add_systems(
-            Startup,
-            (
-                setup,
-                Ball::spawn.after(setup),
-                spawn_paddles.after(setup),
-                spawn_gutters.after(setup),
-                scoreboard::spawn.after(setup),
-            ),
-        )
-        .add_systems(
-            Update,
-            (
-                // ball related systems
-                Ball::movement,
-                Position::project.after(Ball::movement),
-                physics::handle_collisions.after(Ball::movement),
-                
-                // input related systems
-                input::handle,
-
-                // game state management
-                detect_scoring,
-                reset_ball.after(detect_scoring),
-                update_score.after(detect_scoring),
-                scoreboard::update,
-
-                // paddle related systems
-                move_player_paddle.after(input::handle),
-                move_opponent_paddle
-            ),
-        )
+        .add_plugins(PaddlePlugin {
+            mode,
+            replay,
+            ..Default::default()
+        })
         .run();
 }
 
-fn setup(mut commands: Commands) {
-    commands.spawn_empty().insert(Camera2d);
+/// Reads the replay saved at `path`, or the last match when no path is given.
+fn read_replay(path: Option<&str>) -> Result<Replay, String> {
+    let Some(path) = path else {
+        return Replay::load().ok_or_else(|| String::from("No match has been saved to replay yet"));
+    };
+    let text =
+        std::fs::read_to_string(path).map_err(|error| format!("Could not read {path}: {error}"))?;
+    Replay::parse(&text).map_err(|error| format!("Could not parse {path}: {error}"))
 }
@@ -0,0 +1,486 @@
+//! Menu screens shown around a match.
+//!
+//! This module builds the Bevy UI for:
+//! - The main menu, to start a match or open the settings
+//! - The settings screen, to pick single player, local or online versus, four
+//!   player, breakout or practice, the AI difficulty, the volume and sound, the
+//!   points needed to win, power-ups, visual effects, the arena layout and the
+//!   practice drill
+//! - The accessibility screen, under the settings, to pick the palette, slow
+//!   the ball down, scale the scoreboard's text and reduce motion
+//! - The player's record against the AI, under the main menu
+//! - The screen shown while an online match waits for an opponent
+//! - The pause overlay, to resume the match or give it up for the main menu
+//! - The buttons shown under the winner's banner once a match is over, along
+//!   with the match's statistics
+//!
+//! Every screen is scoped to the state it belongs to, so it is despawned as
+//! soon as that state is left.
+
+use bevy::{
+    color::Color,
+    ecs::{
+        change_detection::DetectChanges,
+        component::Component,
+        entity::Entity,
+        hierarchy::ChildSpawnerCommands,
+        query::Changed,
+        system::{Commands, Query, Res, ResMut},
+    },
+    state::state::{NextState, State, StateSet, States, SubStates},
+    state::state_scoped::StateScoped,
+    text::{JustifyText, TextColor, TextFont, TextLayout},
+    ui::{
+        widget::{Button, Text},
+        AlignItems, BackgroundColor, FlexDirection, Interaction, JustifyContent, Node,
+        PositionType, UiRect, Val,
+    },
+    utils::default,
+};
+
+use crate::{
+    accessibility::Palette,
+    ai::Difficulty,
+    components::PlayerSide,
+    effects::EffectsSettings,
+    game::{AppState, GameMode},
+    layout::Layouts,
+    practice::Drills,
+    settings::{MatchHistory, Settings},
+    stats::MatchStats,
+};
+
+const BACKDROP_COLOR: Color = Color::srgba(0., 0., 0., 0.8);
+const BUTTON_COLOR: Color = Color::srgb(0.15, 0.15, 0.15);
+const HOVERED_BUTTON_COLOR: Color = Color::srgb(0.25, 0.25, 0.25);
+const PRESSED_BUTTON_COLOR: Color = Color::srgb(0.35, 0.35, 0.75);
+
+/// Points to win a set that the settings screen cycles through.
+const POINTS_TO_WIN: [usize; 5] = [5, 7, 11, 15, 21];
+
+/// Volumes that the settings screen cycles through.
+const VOLUMES: [f32; 5] = [0., 0.25, 0.5, 0.8, 1.];
+
+/// Fractions of the serve speed that the accessibility screen cycles through.
+const BALL_SPEEDS: [f32; 4] = [0.55, 0.7, 0.85, 1.];
+
+/// Scoreboard text scales that the accessibility screen cycles through.
+const TEXT_SCALES: [f32; 4] = [1., 1.25, 1.5, 2.];
+
+/// Which screen of the main menu is shown.
+#[derive(SubStates, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[source(AppState = AppState::MainMenu)]
+#[states(scoped_entities)]
+pub enum MenuScreen {
+    #[default]
+    Main,
+    Settings,
+    Accessibility,
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuButton {
+    Play,
+    Settings,
+    Back,
+    Mode,
+    Difficulty,
+    Volume,
+    Sound,
+    PointsToWin,
+    PowerUps,
+    Effects,
+    Layout,
+    Drill,
+    Accessibility,
+    Palette,
+    BallSpeed,
+    TextSize,
+    ReducedMotion,
+    Resume,
+    MainMenu,
+    PlayAgain,
+}
+
+impl MenuButton {
+    fn label(self, mode: GameMode, settings: &Settings) -> String {
+        match self {
+            Self::Play => "Play".into(),
+            Self::Settings => "Settings".into(),
+            Self::Back => "Back".into(),
+            Self::Mode => match mode {
+                GameMode::SinglePlayer => "Mode: Single player".into(),
+                GameMode::LocalVersus => "Mode: Local versus".into(),
+                GameMode::OnlineVersus => "Mode: Online versus".into(),
+                GameMode::FourPlayer => "Mode: Four player".into(),
+                GameMode::Breakout => "Mode: Breakout".into(),
+                GameMode::Practice => "Mode: Practice".into(),
+            },
+            Self::Difficulty => format!("Difficulty: {:?}", settings.difficulty),
+            Self::Volume => format!("Volume: {:.0}%", settings.volume * 100.),
+            Self::Sound if settings.muted => "Sound: Off".into(),
+            Self::Sound => "Sound: On".into(),
+            Self::PointsToWin => format!("Points to win: {}", settings.points_to_win),
+            Self::PowerUps if settings.power_ups => "Power-ups: On".into(),
+            Self::PowerUps => "Power-ups: Off".into(),
+            Self::Effects if settings.effects == EffectsSettings::NONE => "Effects: Off".into(),
+            Self::Effects if settings.effects == EffectsSettings::ALL => "Effects: On".into(),
+            Self::Effects => "Effects: Custom".into(),
+            Self::Layout => format!("Arena: {}", settings.layout),
+            Self::Drill => format!("Drill: {}", settings.drill),
+            Self::Accessibility => "Accessibility".into(),
+            Self::Palette => match settings.accessibility.palette {
+                Palette::Classic => "Colors: Classic".into(),
+                Palette::Colorblind => "Colors: Colorblind".into(),
+                Palette::HighContrast => "Colors: High contrast".into(),
+            },
+            Self::BallSpeed => format!(
+                "Ball speed: {:.0}%",
+                settings.accessibility.ball_speed * 100.
+            ),
+            Self::TextSize => format!(
+                "Text size: {:.0}%",
+                settings.accessibility.text_scale * 100.
+            ),
+            Self::ReducedMotion if settings.accessibility.reduced_motion => {
+                "Reduced motion: On".into()
+            }
+            Self::ReducedMotion => "Reduced motion: Off".into(),
+            Self::Resume => "Resume".into(),
+            Self::MainMenu => "Main menu".into(),
+            Self::PlayAgain => "Play again".into(),
+        }
+    }
+}
+
+/// Marks the text of a button so it can be relabelled when a setting changes.
+#[derive(Component)]
+pub struct ButtonLabel(MenuButton);
+
+pub fn spawn_main_menu(
+    mut commands: Commands,
+    mode: Res<GameMode>,
+    settings: Res<Settings>,
+    history: Res<MatchHistory>,
+) {
+    let screen = spawn_screen(
+        &mut commands,
+        MenuScreen::Main,
+        "Paddle",
+        &[MenuButton::Play, MenuButton::Settings],
+        (*mode, &settings),
+    );
+
+    if history.played() == 0 {
+        return;
+    }
+    commands.entity(screen).with_children(|parent| {
+        parent.spawn((
+            Text::new(format!(
+                "Won {} of {} against the AI, best streak {}",
+                history.wins(),
+                history.played(),
+                history.best_streak()
+            )),
+            TextFont {
+                font_size: 20.0,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+            Node {
+                margin: UiRect::top(Val::Px(24.0)),
+                ..default()
+            },
+        ));
+    });
+}
+
+pub fn spawn_settings(mut commands: Commands, mode: Res<GameMode>, settings: Res<Settings>) {
+    spawn_screen(
+        &mut commands,
+        MenuScreen::Settings,
+        "Settings",
+        &[
+            MenuButton::Mode,
+            MenuButton::Difficulty,
+            MenuButton::Volume,
+            MenuButton::Sound,
+            MenuButton::PointsToWin,
+            MenuButton::PowerUps,
+            MenuButton::Effects,
+            MenuButton::Layout,
+            MenuButton::Drill,
+            MenuButton::Accessibility,
+            MenuButton::Back,
+        ],
+        (*mode, &settings),
+    );
+}
+
+pub fn spawn_accessibility(mut commands: Commands, mode: Res<GameMode>, settings: Res<Settings>) {
+    spawn_screen(
+        &mut commands,
+        MenuScreen::Accessibility,
+        "Accessibility",
+        &[
+            MenuButton::Palette,
+            MenuButton::BallSpeed,
+            MenuButton::TextSize,
+            MenuButton::ReducedMotion,
+            MenuButton::Back,
+        ],
+        (*mode, &settings),
+    );
+}
+
+pub fn spawn_matchmaking(mut commands: Commands, mode: Res<GameMode>, settings: Res<Settings>) {
+    spawn_screen(
+        &mut commands,
+        AppState::Matchmaking,
+        "Waiting for an opponent",
+        &[MenuButton::MainMenu],
+        (*mode, &settings),
+    );
+}
+
+pub fn spawn_pause_menu(mut commands: Commands, mode: Res<GameMode>, settings: Res<Settings>) {
+    spawn_screen(
+        &mut commands,
+        AppState::Paused,
+        "Paused",
+        &[MenuButton::Resume, MenuButton::MainMenu],
+        (*mode, &settings),
+    );
+}
+
+/// Adds the game over buttons below the scoreboard's banner, with the match's
+/// statistics under them, leaving the rest of the arena visible.
+pub fn spawn_game_over_menu(
+    mut commands: Commands,
+    mode: Res<GameMode>,
+    settings: Res<Settings>,
+    stats: Res<MatchStats>,
+    side: PlayerSide,
+) {
+    commands
+        .spawn((
+            StateScoped(AppState::GameOver),
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Percent(60.0),
+                width: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(12.0),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            for button in [MenuButton::PlayAgain, MenuButton::MainMenu] {
+                spawn_button(parent, button, button.label(*mode, &settings));
+            }
+            parent.spawn((
+                Text::new(stats.summary(*mode, side.edge())),
+                TextFont {
+                    font_size: 20.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+                TextLayout::new_with_justify(JustifyText::Center),
+                Node {
+                    margin: UiRect::top(Val::Px(24.0)),
+                    ..default()
+                },
+            ));
+        });
+}
+
+/// Spawns a full screen menu with a title above a column of buttons, and
+/// returns the screen so more can be added to it.
+fn spawn_screen<S: States>(
+    commands: &mut Commands,
+    state: S,
+    title: &str,
+    buttons: &[MenuButton],
+    (mode, settings): (GameMode, &Settings),
+) -> Entity {
+    commands
+        .spawn((
+            StateScoped(state),
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(12.0),
+                ..default()
+            },
+            BackgroundColor(BACKDROP_COLOR),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(title),
+                TextFont {
+                    font_size: 72.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+                Node {
+                    margin: UiRect::bottom(Val::Px(24.0)),
+                    ..default()
+                },
+            ));
+
+            for &button in buttons {
+                spawn_button(parent, button, button.label(mode, settings));
+            }
+        })
+        .id()
+}
+
+fn spawn_button(parent: &mut ChildSpawnerCommands, button: MenuButton, label: String) {
+    parent
+        .spawn((
+            button,
+            Button,
+            Node {
+                width: Val::Px(320.0),
+                padding: UiRect::axes(Val::Px(16.0), Val::Px(10.0)),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            BackgroundColor(BUTTON_COLOR),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                ButtonLabel(button),
+                Text::new(label),
+                TextFont {
+                    font_size: 28.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+                TextLayout::new_with_justify(JustifyText::Center),
+            ));
+        });
+}
+
+/// Shades buttons as they are hovered and pressed.
+pub fn highlight(mut buttons: Query<(&Interaction, &mut BackgroundColor), Changed<Interaction>>) {
+    for (interaction, mut color) in &mut buttons {
+        color.0 = match interaction {
+            Interaction::Pressed => PRESSED_BUTTON_COLOR,
+            Interaction::Hovered => HOVERED_BUTTON_COLOR,
+            Interaction::None => BUTTON_COLOR,
+        };
+    }
+}
+
+pub fn press(
+    buttons: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+    mut next_state: ResMut<NextState<AppState>>,
+    (screen, mut next_screen): (
+        Option<Res<State<MenuScreen>>>,
+        ResMut<NextState<MenuScreen>>,
+    ),
+    mut mode: ResMut<GameMode>,
+    mut settings: ResMut<Settings>,
+    layouts: Res<Layouts>,
+    drills: Res<Drills>,
+) {
+    for (interaction, button) in &buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match button {
+            MenuButton::Play | MenuButton::PlayAgain => next_state.set(mode.starting_state()),
+            MenuButton::Resume => next_state.set(AppState::Playing),
+            MenuButton::MainMenu => next_state.set(AppState::MainMenu),
+            MenuButton::Settings => next_screen.set(MenuScreen::Settings),
+            // Accessibility is one level further down, under the settings
+            MenuButton::Back => match screen.as_deref().map(State::get) {
+                Some(MenuScreen::Accessibility) => next_screen.set(MenuScreen::Settings),
+                _ => next_screen.set(MenuScreen::Main),
+            },
+            MenuButton::Mode => {
+                *mode = match *mode {
+                    GameMode::SinglePlayer => GameMode::LocalVersus,
+                    GameMode::LocalVersus => GameMode::OnlineVersus,
+                    GameMode::OnlineVersus => GameMode::FourPlayer,
+                    GameMode::FourPlayer => GameMode::Breakout,
+                    GameMode::Breakout => GameMode::Practice,
+                    GameMode::Practice => GameMode::SinglePlayer,
+                };
+            }
+            MenuButton::Difficulty => {
+                settings.difficulty = match settings.difficulty {
+                    Difficulty::Easy => Difficulty::Medium,
+                    Difficulty::Medium => Difficulty::Hard,
+                    Difficulty::Hard => Difficulty::Easy,
+                };
+            }
+            MenuButton::Volume => {
+                settings.volume = VOLUMES
+                    .iter()
+                    .copied()
+                    .find(|&volume| volume > settings.volume)
+                    .unwrap_or(VOLUMES[0]);
+            }
+            MenuButton::Sound => settings.muted = !settings.muted,
+            MenuButton::PointsToWin => {
+                settings.points_to_win = POINTS_TO_WIN
+                    .iter()
+                    .copied()
+                    .find(|&points| points > settings.points_to_win)
+                    .unwrap_or(POINTS_TO_WIN[0]);
+            }
+            MenuButton::PowerUps => settings.power_ups = !settings.power_ups,
+            // Anything but every effect turns them all on
+            MenuButton::Effects if settings.effects == EffectsSettings::ALL => {
+                settings.effects = EffectsSettings::NONE;
+            }
+            MenuButton::Effects => settings.effects = EffectsSettings::ALL,
+            MenuButton::Layout => settings.layout = layouts.after(&settings.layout).name.clone(),
+            MenuButton::Drill => settings.drill = drills.after(&settings.drill).name.clone(),
+            MenuButton::Accessibility => next_screen.set(MenuScreen::Accessibility),
+            MenuButton::Palette => {
+                settings.accessibility.palette = settings.accessibility.palette.next();
+            }
+            MenuButton::BallSpeed => {
+                settings.accessibility.ball_speed = BALL_SPEEDS
+                    .iter()
+                    .copied()
+                    .find(|&speed| speed > settings.accessibility.ball_speed)
+                    .unwrap_or(BALL_SPEEDS[0]);
+            }
+            MenuButton::TextSize => {
+                settings.accessibility.text_scale = TEXT_SCALES
+                    .iter()
+                    .copied()
+                    .find(|&scale| scale > settings.accessibility.text_scale)
+                    .unwrap_or(TEXT_SCALES[0]);
+            }
+            MenuButton::ReducedMotion => {
+                settings.accessibility.reduced_motion = !settings.accessibility.reduced_motion;
+            }
+        }
+    }
+}
+
+/// Relabels the buttons whenever one of the settings they show changes.
+pub fn update_labels(
+    mut labels: Query<(&mut Text, &ButtonLabel)>,
+    mode: Res<GameMode>,
+    settings: Res<Settings>,
+) {
+    if !(mode.is_changed() || settings.is_changed()) {
+        return;
+    }
+
+    for (mut text, label) in &mut labels {
+        text.0 = label.0.label(*mode, &settings);
+    }
+}
@@ -5,22 +5,56 @@
 //!
 //! The main components are:
 //! - `Collision` enum representing different collision sides
-//! - `detect_collision` function for precise collision detection
-//! - `handle_collisions` system that updates velocities based on collisions
+//! - `Contact` describing when and where the ball touched a wall during a tick
+//! - `detect_collision` function for swept (continuous) collision detection
+//! - `paddle_bounce` function deciding the angle the ball leaves a paddle at
+//! - `handle_collisions` system that resolves penetration and updates velocities based on collisions,
+//!   for every ball in play, and remembers which paddle last touched each ball
+//! - `BallCollided` event sent for every bounce, which the sound effects play from
 //!
-//! The collision detection uses a combination of broad-phase (bounding volume intersection)
-//! and narrow-phase (closest point calculation) to determine exact collision sides.
+//! The ball is swept along the path it travelled during the fixed tick rather than only
+//! tested where it ended up, so a fast ball cannot tunnel through a thin paddle. The sweep
+//! treats the wall as a box grown by the ball's radius (with rounded corners) and casts the
+//! ball's center against it to find the time of impact.
+//!
+//! Walls reflect the ball like a mirror. Paddle faces instead send it off at an angle that
+//! depends on where it struck (flat from the center, steep from the edges) and on how the
+//! paddle was moving, and every paddle hit makes the ball a little faster.
+
+use std::f32::consts::{FRAC_PI_3, PI};
 
 use bevy::{
     ecs::{
-        query::{With, Without},
-        system::Query,
+        entity::Entity,
+        event::{Event, EventWriter},
+        query::{Has, With, Without},
+        system::{Query, Res},
     },
-    math::bounding::{Aabb2d, BoundingCircle, BoundingVolume, IntersectsVolume},
+    math::{
+        bounding::{Aabb2d, BoundingCircle, BoundingVolume, IntersectsVolume},
+        Vec2,
+    },
+};
+
+use crate::{
+    breakout::Brick,
+    components::{Ball, Edge, LastTouched, Position, PreviousPosition, Shape, Speed, Velocity},
+    layout::Obstacle,
+    powerup::PowerUp,
+    tuning::PaddleTuning,
 };
 
-use crate::components::{Ball, Position, Shape, Velocity};
+/// A fast ball can touch more than one wall in a single tick, for example when it
+/// hits a paddle right next to a gutter. Contacts beyond this are left for the next tick.
+const MAX_CONTACTS_PER_TICK: usize = 4;
+
+/// Steepest angle, measured from the paddle face's normal, the ball can leave a paddle at.
+const MAX_BOUNCE_ANGLE: f32 = FRAC_PI_3;
 
+/// How far a paddle moving at full speed turns the ball in its direction of travel.
+const SPIN_ANGLE: f32 = PI / 12.0;
+
+/// The face of the wall the ball touched.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum Collision {
     Left,
@@ -29,16 +63,190 @@ pub enum Collision {
     Bottom,
 }
 
-fn detect_collision(ball: BoundingCircle, wall: Aabb2d) -> Option<Collision> {
-    if !ball.intersects(&wall) {
+impl Collision {
+    /// Unit vector pointing out of the touched face.
+    pub fn normal(self) -> Vec2 {
+        match self {
+            Collision::Left => Vec2::NEG_X,
+            Collision::Right => Vec2::X,
+            Collision::Top => Vec2::Y,
+            Collision::Bottom => Vec2::NEG_Y,
+        }
+    }
+
+    /// Unit vector along the touched face, up or to the right.
+    pub fn tangent(self) -> Vec2 {
+        match self {
+            Collision::Left | Collision::Right => Vec2::Y,
+            Collision::Top | Collision::Bottom => Vec2::X,
+        }
+    }
+}
+
+/// What the ball bounced off.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum CollisionKind {
+    Paddle,
+    Gutter,
+    Obstacle,
+    Brick,
+}
+
+/// Sent every time a ball bounces off anything in the arena: a paddle, a
+/// gutter, an obstacle or a brick.
+#[derive(Event, Debug, PartialEq, Copy, Clone)]
+pub struct BallCollided {
+    pub ball: Entity,
+    /// What the ball bounced off, of the `kind` below.
+    pub wall: Entity,
+    /// The face of the wall the ball touched.
+    pub with: Collision,
+    pub kind: CollisionKind,
+    /// Point on the wall's surface the ball touched.
+    pub point: Vec2,
+    /// How fast the ball leaves the wall, in units per second.
+    pub speed: f32,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Contact {
+    pub side: Collision,
+    /// Fraction of the motion travelled before touching the wall, in `0..=1`.
+    pub time: f32,
+    /// Where the ball's center has to be to just touch the wall without overlapping it.
+    pub center: Vec2,
+    /// Point on the wall's surface the ball touched.
+    pub point: Vec2,
+}
+
+/// Sweeps `ball` along `motion` and reports the first time it touches `wall`.
+///
+/// A ball that already overlaps the wall and is moving further into it is reported
+/// as touching at time zero, with `center` pushed back out of the wall. A ball moving
+/// away from a wall it overlaps is left alone so it is not reflected twice.
+pub fn detect_collision(ball: BoundingCircle, motion: Vec2, wall: Aabb2d) -> Option<Contact> {
+    if ball.intersects(&wall) {
+        return resolve_overlap(ball, motion, wall);
+    }
+
+    let start = ball.center();
+    let radius = ball.radius();
+    let grown = Aabb2d {
+        min: wall.min - radius,
+        max: wall.max + radius,
+    };
+
+    let (enter_x, exit_x) = slab(start.x, motion.x, grown.min.x, grown.max.x)?;
+    let (enter_y, exit_y) = slab(start.y, motion.y, grown.min.y, grown.max.y)?;
+
+    let enter = enter_x.max(enter_y);
+    let exit = exit_x.min(exit_y);
+    if enter > exit || !(0.0..=1.0).contains(&enter) {
+        return None;
+    }
+
+    let center = start + motion * enter;
+
+    // The grown box has square corners but the real shape is rounded there, so a
+    // center entering a corner region has to be tested against the corner itself.
+    let beyond_x = center.x < wall.min.x || center.x > wall.max.x;
+    let beyond_y = center.y < wall.min.y || center.y > wall.max.y;
+    if beyond_x && beyond_y {
+        let corner = wall.closest_point(center);
+        let time = sweep_point(start, motion, corner, radius)?;
+        let center = start + motion * time;
+        return Some(Contact {
+            side: side_from_offset(center - corner),
+            time,
+            center,
+            point: corner,
+        });
+    }
+
+    let side = if enter_x > enter_y {
+        if motion.x > 0.0 {
+            Collision::Left
+        } else {
+            Collision::Right
+        }
+    } else if motion.y < 0.0 {
+        Collision::Top
+    } else {
+        Collision::Bottom
+    };
+
+    Some(Contact {
+        side,
+        time: enter,
+        center,
+        point: wall.closest_point(center),
+    })
+}
+
+fn resolve_overlap(ball: BoundingCircle, motion: Vec2, wall: Aabb2d) -> Option<Contact> {
+    let start = ball.center();
+    let reach = wall.half_size() + ball.radius();
+    let local = start - wall.center();
+    let depth = reach - local.abs();
+
+    // Push out along the axis that needs the smallest correction
+    let side = if depth.x < depth.y {
+        if local.x < 0.0 {
+            Collision::Left
+        } else {
+            Collision::Right
+        }
+    } else if local.y > 0.0 {
+        Collision::Top
+    } else {
+        Collision::Bottom
+    };
+
+    let normal = side.normal();
+    if motion.dot(normal) >= 0.0 {
+        return None;
+    }
+
+    let center = start + normal * (normal * depth).abs().max_element();
+    Some(Contact {
+        side,
+        time: 0.0,
+        center,
+        point: wall.closest_point(center),
+    })
+}
+
+/// Returns the times `start + motion * t` enters and exits the range `min..=max`.
+fn slab(start: f32, motion: f32, min: f32, max: f32) -> Option<(f32, f32)> {
+    if motion == 0.0 {
+        return (min..=max)
+            .contains(&start)
+            .then_some((f32::NEG_INFINITY, f32::INFINITY));
+    }
+
+    let a = (min - start) / motion;
+    let b = (max - start) / motion;
+    Some((a.min(b), a.max(b)))
+}
+
+/// Returns the first time in `0..=1` the moving center comes within `radius` of `point`.
+fn sweep_point(start: Vec2, motion: Vec2, point: Vec2, radius: f32) -> Option<f32> {
+    let offset = start - point;
+    let a = motion.length_squared();
+    let b = 2.0 * motion.dot(offset);
+    let c = offset.length_squared() - radius * radius;
+
+    let discriminant = b * b - 4.0 * a * c;
+    if a == 0.0 || discriminant < 0.0 {
         return None;
     }
 
-    let center = ball.center();
-    let closest = wall.closest_point(center);
-    let offset = center - closest;
+    let time = (-b - discriminant.sqrt()) / (2.0 * a);
+    (0.0..=1.0).contains(&time).then_some(time)
+}
 
-    let side = if offset.x.abs() > offset.y.abs() {
+fn side_from_offset(offset: Vec2) -> Collision {
+    if offset.x.abs() > offset.y.abs() {
         if offset.x < 0.0 {
             Collision::Left
         } else {
@@ -48,36 +256,287 @@ fn detect_collision(ball: BoundingCircle, wall: Aabb2d) -> Option<Collision> {
         Collision::Top
     } else {
         Collision::Bottom
-    };
+    }
+}
 
-    Some(side)
+/// Direction the ball leaves a paddle's `side` face in.
+///
+/// `offset` is where the ball struck the face along its `tangent`, from -1 at its bottom
+/// (or left) edge to 1 at its top (or right) edge, and `spin` is the paddle's velocity
+/// direction along the face at the time.
+pub fn paddle_bounce(side: Collision, offset: f32, spin: f32) -> Vec2 {
+    let angle = offset.clamp(-1.0, 1.0) * MAX_BOUNCE_ANGLE + spin * SPIN_ANGLE;
+    let angle = angle.clamp(-MAX_BOUNCE_ANGLE, MAX_BOUNCE_ANGLE);
+    side.normal() * angle.cos() + side.tangent() * angle.sin()
 }
 
+/// A ball as it is swept along its path: where it started and ended the tick,
+/// where it is heading and how fast, its size and who touched it last.
+type SweptBall<'a> = (
+    Entity,
+    &'a mut Position,
+    &'a PreviousPosition,
+    &'a mut Velocity,
+    &'a mut Speed,
+    &'a Shape,
+    &'a mut LastTouched,
+);
+
+/// Anything a ball bounces off, with the velocity and edge of paddles and
+/// whether it is an obstacle or a brick.
+type Collider<'a> = (
+    Entity,
+    &'a Position,
+    &'a Shape,
+    Option<&'a Velocity>,
+    Option<&'a Edge>,
+    (Has<Obstacle>, Has<Brick>),
+);
+
 pub fn handle_collisions(
-    mut ball: Query<(&mut Velocity, &Position, &Shape), With<Ball>>,
-    game_objects: Query<(&Position, &Shape), Without<Ball>>,
+    mut balls: Query<SweptBall, With<Ball>>,
+    game_objects: Query<Collider, (Without<Ball>, Without<PowerUp>)>,
+    mut collided: EventWriter<BallCollided>,
+    tuning: Res<PaddleTuning>,
 ) {
-    if let Ok((mut ball_velocity, ball_position, ball_shape)) = ball.get_single_mut() {
-        for (position, shape) in &game_objects {
-            if let Some(collision) = detect_collision(
-                BoundingCircle::new(ball_position.coords, ball_shape.size.x),
-                Aabb2d::new(position.coords, shape.size / 2.0),
-            ) {
-                match collision {
-                    Collision::Left => {
-                        ball_velocity.direction.x *= -1.;
-                    }
-                    Collision::Right => {
-                        ball_velocity.direction.x *= -1.;
-                    }
-                    Collision::Top => {
-                        ball_velocity.direction.y *= -1.;
-                    }
-                    Collision::Bottom => {
-                        ball_velocity.direction.y *= -1.;
-                    }
-                }
+    for (
+        ball,
+        mut ball_position,
+        previous,
+        mut ball_velocity,
+        mut speed,
+        ball_shape,
+        mut last_touched,
+    ) in &mut balls
+    {
+        let radius = ball_shape.size.x;
+        let mut start = previous.0;
+        let mut motion = ball_position.coords - start;
+
+        for _ in 0..MAX_CONTACTS_PER_TICK {
+            let first_contact = game_objects
+                .iter()
+                .filter_map(|(entity, position, shape, velocity, edge, kind)| {
+                    let contact = detect_collision(
+                        BoundingCircle::new(start, radius),
+                        motion,
+                        Aabb2d::new(position.coords, shape.size / 2.0),
+                    )?;
+                    Some((contact, entity, position, shape, velocity, edge, kind))
+                })
+                .min_by(|(a, ..), (b, ..)| a.time.total_cmp(&b.time));
+
+            let Some((contact, entity, position, shape, velocity, edge, (is_obstacle, is_brick))) =
+                first_contact
+            else {
+                break;
+            };
+
+            // Only paddles guard an edge
+            let kind = if edge.is_some() {
+                CollisionKind::Paddle
+            } else if is_obstacle {
+                CollisionKind::Obstacle
+            } else if is_brick {
+                CollisionKind::Brick
+            } else {
+                CollisionKind::Gutter
+            };
+            let normal = contact.side.normal();
+            let remaining = motion * (1.0 - contact.time);
+            start = contact.center;
+
+            // The face of a paddle is the side that runs along its edge
+            let tangent = contact.side.tangent();
+            let paddle_face = edge.is_some_and(|edge| edge.axis() == tangent);
+            if paddle_face {
+                let reach = shape.size.dot(tangent) / 2.0 + radius;
+                let offset = (contact.center - position.coords).dot(tangent) / reach;
+                let spin = velocity.map_or(0.0, |velocity| velocity.direction.dot(tangent));
+
+                ball_velocity.direction = paddle_bounce(contact.side, offset, spin);
+                motion = ball_velocity.direction * remaining.length();
+                speed.ramp(&tuning);
+                last_touched.0 = Some(entity);
+                collided.write(BallCollided {
+                    ball,
+                    wall: entity,
+                    with: contact.side,
+                    kind,
+                    point: contact.point,
+                    speed: speed.0,
+                });
+                continue;
             }
+
+            // Reflect whatever is left of this tick's motion off the touched face
+            motion = remaining - 2.0 * remaining.dot(normal) * normal;
+
+            let into_wall = ball_velocity.direction.dot(normal);
+            if into_wall < 0.0 {
+                ball_velocity.direction -= 2.0 * into_wall * normal;
+            }
+            collided.write(BallCollided {
+                ball,
+                wall: entity,
+                with: contact.side,
+                kind,
+                point: contact.point,
+                speed: speed.0,
+            });
         }
+
+        ball_position.coords = start + motion;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    const RADIUS: f32 = 5.0;
+
+    fn paddle() -> Aabb2d {
+        // As thin as a real paddle
+        Aabb2d::new(Vec2::ZERO, Vec2::new(5.0, 25.0))
+    }
+
+    fn ball_at(x: f32, y: f32) -> BoundingCircle {
+        BoundingCircle::new(Vec2::new(x, y), RADIUS)
+    }
+
+    #[test]
+    fn fast_ball_moving_right_hits_left_face() {
+        // Ends the tick well past the paddle, so an overlap test would miss it
+        let contact = detect_collision(ball_at(-100.0, 0.0), Vec2::new(200.0, 0.0), paddle())
+            .expect("ball should not tunnel through the paddle");
+
+        assert_eq!(contact.side, Collision::Left);
+        assert_eq!(contact.center, Vec2::new(-5.0 - RADIUS, 0.0));
+        assert_eq!(contact.point, Vec2::new(-5.0, 0.0));
+        assert!((contact.time - 0.45).abs() < 1e-6);
+    }
+
+    #[test]
+    fn fast_ball_moving_left_hits_right_face() {
+        let contact =
+            detect_collision(ball_at(100.0, 10.0), Vec2::new(-200.0, -10.0), paddle()).unwrap();
+
+        assert_eq!(contact.side, Collision::Right);
+        assert_eq!(contact.center.x, 5.0 + RADIUS);
+    }
+
+    #[test]
+    fn fast_ball_moving_down_hits_top_face() {
+        let gutter = Aabb2d::new(Vec2::ZERO, Vec2::new(640.0, 10.0));
+        let contact =
+            detect_collision(ball_at(0.0, 200.0), Vec2::new(30.0, -400.0), gutter).unwrap();
+
+        assert_eq!(contact.side, Collision::Top);
+        assert_eq!(contact.center.y, 10.0 + RADIUS);
+    }
+
+    #[test]
+    fn fast_ball_moving_up_hits_bottom_face() {
+        let gutter = Aabb2d::new(Vec2::ZERO, Vec2::new(640.0, 10.0));
+        let contact =
+            detect_collision(ball_at(0.0, -200.0), Vec2::new(-30.0, 400.0), gutter).unwrap();
+
+        assert_eq!(contact.side, Collision::Bottom);
+        assert_eq!(contact.center.y, -10.0 - RADIUS);
+    }
+
+    #[test]
+    fn ball_passing_beside_paddle_does_not_collide() {
+        let contact = detect_collision(ball_at(-100.0, 40.0), Vec2::new(200.0, 0.0), paddle());
+        assert_eq!(contact, None);
+    }
+
+    #[test]
+    fn ball_stopping_short_of_paddle_does_not_collide() {
+        let contact = detect_collision(ball_at(-100.0, 0.0), Vec2::new(50.0, 0.0), paddle());
+        assert_eq!(contact, None);
+    }
+
+    #[test]
+    fn ball_clipping_corner_hits_rounded_corner() {
+        let contact = detect_collision(
+            ball_at(-100.0, 25.0 + RADIUS * 0.5),
+            Vec2::new(200.0, 0.0),
+            paddle(),
+        )
+        .unwrap();
+
+        assert_eq!(contact.side, Collision::Left);
+        assert_eq!(contact.point, Vec2::new(-5.0, 25.0));
+        assert!((contact.center.distance(contact.point) - RADIUS).abs() < 1e-3);
+    }
+
+    #[test]
+    fn ball_missing_rounded_corner_does_not_collide() {
+        // Crosses the square corner of the grown box, but stays further than the
+        // radius from the paddle's actual corner
+        let closest = Vec2::new(-5.0 - RADIUS * 0.9, 25.0 + RADIUS * 0.9);
+        let start = closest - Vec2::splat(50.0);
+        let contact = detect_collision(
+            BoundingCircle::new(start, RADIUS),
+            Vec2::splat(100.0),
+            paddle(),
+        );
+
+        assert_eq!(contact, None);
+    }
+
+    #[test]
+    fn embedded_ball_moving_in_is_pushed_out() {
+        let contact = detect_collision(ball_at(-6.0, 0.0), Vec2::new(10.0, 0.0), paddle()).unwrap();
+
+        assert_eq!(contact.side, Collision::Left);
+        assert_eq!(contact.time, 0.0);
+        assert_eq!(contact.center, Vec2::new(-5.0 - RADIUS, 0.0));
+    }
+
+    #[test]
+    fn embedded_ball_moving_out_is_not_reflected_again() {
+        let contact = detect_collision(ball_at(-6.0, 0.0), Vec2::new(-10.0, 0.0), paddle());
+        assert_eq!(contact, None);
+    }
+
+    #[test]
+    fn center_paddle_hit_leaves_flat() {
+        assert_eq!(paddle_bounce(Collision::Left, 0.0, 0.0), Vec2::NEG_X);
+        assert_eq!(paddle_bounce(Collision::Right, 0.0, 0.0), Vec2::X);
+    }
+
+    #[test]
+    fn edge_paddle_hit_leaves_steep_and_clamped() {
+        let top_edge = paddle_bounce(Collision::Right, 1.0, 0.0);
+        assert!((top_edge.to_angle() - MAX_BOUNCE_ANGLE).abs() < 1e-5);
+
+        let past_bottom_edge = paddle_bounce(Collision::Left, -3.0, -1.0);
+        assert!(past_bottom_edge.x < 0.0);
+        assert!((past_bottom_edge.y + MAX_BOUNCE_ANGLE.sin()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn top_and_bottom_paddles_bounce_along_their_face() {
+        assert_eq!(paddle_bounce(Collision::Bottom, 0.0, 0.0), Vec2::NEG_Y);
+
+        let right_edge = paddle_bounce(Collision::Top, 1.0, 0.0);
+        assert!(right_edge.x > 0.0 && right_edge.y > 0.0);
+        assert!((right_edge.to_angle() - (FRAC_PI_2 - MAX_BOUNCE_ANGLE)).abs() < 1e-5);
+    }
+
+    #[test]
+    fn moving_paddle_adds_spin() {
+        let still = paddle_bounce(Collision::Right, 0.25, 0.0);
+        let moving_up = paddle_bounce(Collision::Right, 0.25, 1.0);
+        let moving_down = paddle_bounce(Collision::Right, 0.25, -1.0);
+
+        assert!(moving_up.y > still.y);
+        assert!(moving_down.y < still.y);
+        assert!((moving_up.length() - 1.0).abs() < 1e-5);
     }
 }
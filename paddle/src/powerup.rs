@@ -0,0 +1,432 @@
+//! Collectible power-ups.
+//!
+//! When the `MatchRules` allow them, outside of practice, a power-up appears
+//! somewhere in the midfield every few seconds of a rally. The first ball to pass through it
+//! collects it:
+//! - `Enlarge` and `Shrink` resize the paddle that last touched the ball
+//! - `MultiBall` splits the ball into three
+//! - `SpeedBurst` makes the ball travel faster
+//! - `Sticky` makes the paddle that last touched the ball catch it for a moment
+//!   on every hit before sending it back
+//!
+//! Everything but `MultiBall` is timed: it is held as a `Modifier` on the
+//! paddle or ball it applies to and undone once it expires. Where power-ups
+//! appear and what they do is drawn from the `GameRng`, so replays see the
+//! same ones.
+
+use std::{f32::consts::PI, time::Duration};
+
+use bevy::{
+    asset::Assets,
+    ecs::{
+        change_detection::DetectChanges,
+        component::Component,
+        entity::Entity,
+        event::EventReader,
+        query::{Added, Changed, With, Without},
+        resource::Resource,
+        system::{Commands, Query, Res, ResMut},
+        world::Ref,
+    },
+    math::{
+        bounding::{Aabb2d, BoundingCircle, IntersectsVolume},
+        primitives::Rectangle,
+        Rot2, Vec2,
+    },
+    render::mesh::{Mesh, Mesh2d},
+    sprite::{ColorMaterial, MeshMaterial2d},
+    time::{Time, Timer, TimerMode},
+};
+use rand::Rng;
+
+use crate::{
+    accessibility::ArenaColors,
+    arena::Arena,
+    components::{
+        Ball, Edge, LastTouched, Paddle, Position, PreviousPosition, Shape, Speed, Velocity,
+    },
+    game::{GameMode, GameRng, MatchRules, RallyEnded},
+    physics::detect_collision,
+    tuning::PaddleTuning,
+};
+
+/// How often a new power-up appears while the ball is in play.
+const SPAWN_INTERVAL: Duration = Duration::from_secs(6);
+
+/// Most power-ups waiting to be collected at once.
+const MAX_POWER_UPS: usize = 2;
+
+const POWER_UP_SIZE: f32 = 24.;
+
+/// Fraction of the arena's half width, either side of the center line, that
+/// power-ups appear in.
+const MIDFIELD: f32 = 0.25;
+
+/// How long a timed power-up lasts.
+const DURATION: Duration = Duration::from_secs(8);
+
+const ENLARGE_FACTOR: f32 = 1.6;
+const SHRINK_FACTOR: f32 = 0.6;
+const SPEED_BURST_FACTOR: f32 = 1.5;
+
+/// How long a sticky paddle holds on to the ball.
+const STICK_TIME: Duration = Duration::from_millis(400);
+
+/// Angle between the balls a multi-ball splits into.
+const MULTI_BALL_SPREAD: f32 = PI / 9.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerUpKind {
+    Enlarge,
+    Shrink,
+    MultiBall,
+    SpeedBurst,
+    Sticky,
+}
+
+impl PowerUpKind {
+    const ALL: [Self; 5] = [
+        Self::Enlarge,
+        Self::Shrink,
+        Self::MultiBall,
+        Self::SpeedBurst,
+        Self::Sticky,
+    ];
+}
+
+/// A power-up waiting in the midfield to be collected.
+#[derive(Component, Debug, Clone, Copy)]
+#[require(Position, Shape = Shape { size: Vec2::splat(POWER_UP_SIZE) })]
+pub struct PowerUp(pub PowerUpKind);
+
+/// A timed power-up applying to the paddle or ball it is on. Collecting
+/// another one replaces it.
+#[derive(Component, Debug, Clone)]
+pub struct Modifier {
+    pub kind: PowerUpKind,
+    pub timer: Timer,
+}
+
+impl Modifier {
+    fn new(kind: PowerUpKind) -> Self {
+        Self {
+            kind,
+            timer: Timer::new(DURATION, TimerMode::Once),
+        }
+    }
+
+    /// How much faster than its `Speed` a ball with this modifier travels.
+    pub fn speed_factor(modifier: Option<&Self>) -> f32 {
+        match modifier {
+            Some(Self {
+                kind: PowerUpKind::SpeedBurst,
+                ..
+            }) => SPEED_BURST_FACTOR,
+            _ => 1.,
+        }
+    }
+
+    /// Size of a paddle on `edge` with this modifier.
+    pub fn paddle_size(modifier: Option<&Self>, edge: Edge, tuning: &PaddleTuning) -> Vec2 {
+        let factor = modifier.map_or(1., |modifier| resize_factor(modifier.kind));
+        edge.orient(tuning.paddle_size() * Vec2::new(1., factor))
+    }
+}
+
+/// A ball caught by a sticky paddle, carried along with it until it is
+/// released in the direction it was hit.
+#[derive(Component, Debug, Clone)]
+pub struct Stuck {
+    paddle: Entity,
+    offset: Vec2,
+    direction: Vec2,
+    timer: Timer,
+}
+
+/// Times the next power-up.
+#[derive(Resource, Debug, Clone)]
+pub struct PowerUpSpawner(pub Timer);
+
+impl Default for PowerUpSpawner {
+    fn default() -> Self {
+        Self(Timer::new(SPAWN_INTERVAL, TimerMode::Repeating))
+    }
+}
+
+/// Run condition for everything in this module.
+pub fn enabled(rules: Res<MatchRules>, mode: Res<GameMode>) -> bool {
+    rules.power_ups && *mode != GameMode::Practice
+}
+
+/// Places a random power-up in the midfield every `SPAWN_INTERVAL`.
+pub fn spawn(
+    mut commands: Commands,
+    mut spawner: ResMut<PowerUpSpawner>,
+    power_ups: Query<(), With<PowerUp>>,
+    arena: Res<Arena>,
+    time: Res<Time>,
+    mut rng: ResMut<GameRng>,
+) {
+    if !spawner.0.tick(time.delta()).just_finished() || power_ups.iter().len() >= MAX_POWER_UPS {
+        return;
+    }
+
+    let max_x = arena.half_width() * MIDFIELD;
+    let max_y = arena.half_height() / 2.;
+    let coords = Vec2::new(rng.gen_range(-max_x..=max_x), rng.gen_range(-max_y..=max_y));
+    let kind = PowerUpKind::ALL[rng.gen_range(0..PowerUpKind::ALL.len())];
+
+    commands.spawn((PowerUp(kind), Position { coords }));
+}
+
+/// A ball with the path it took this tick, and what it carries to a power-up.
+type PassingBall<'a> = (
+    Entity,
+    &'a Position,
+    &'a PreviousPosition,
+    &'a Velocity,
+    &'a Speed,
+    &'a Shape,
+    &'a LastTouched,
+);
+
+/// Paddles, kept apart from the balls and power-ups that also have a shape.
+type OnlyPaddles = (With<Paddle>, Without<Ball>, Without<PowerUp>);
+
+/// Hands each power-up a ball passes through to that ball, or to the paddle
+/// that last touched it.
+pub fn collect(
+    mut commands: Commands,
+    power_ups: Query<(Entity, &Position, &Shape, &PowerUp), Without<Paddle>>,
+    balls: Query<PassingBall, With<Ball>>,
+    mut paddles: Query<(&mut Shape, &Edge), OnlyPaddles>,
+    tuning: Res<PaddleTuning>,
+) {
+    for (power_up, power_up_position, power_up_shape, &PowerUp(kind)) in &power_ups {
+        let area = Aabb2d::new(power_up_position.coords, power_up_shape.size / 2.);
+        let Some((ball, position, _, velocity, speed, shape, last_touched)) =
+            balls
+                .iter()
+                .find(|(_, position, previous, _, _, shape, _)| {
+                    let radius = shape.size.x;
+                    BoundingCircle::new(position.coords, radius).intersects(&area)
+                        || detect_collision(
+                            BoundingCircle::new(previous.0, radius),
+                            position.coords - previous.0,
+                            area,
+                        )
+                        .is_some()
+                })
+        else {
+            continue;
+        };
+
+        commands.entity(power_up).despawn();
+
+        match kind {
+            PowerUpKind::Enlarge | PowerUpKind::Shrink | PowerUpKind::Sticky => {
+                let Some(paddle) = last_touched.0 else {
+                    continue;
+                };
+                let Ok((mut shape, &edge)) = paddles.get_mut(paddle) else {
+                    continue;
+                };
+                // Replaces whatever the paddle had before, size included
+                let modifier = Modifier::new(kind);
+                shape.size = Modifier::paddle_size(Some(&modifier), edge, &tuning);
+                commands.entity(paddle).insert(modifier);
+            }
+            PowerUpKind::SpeedBurst => {
+                commands.entity(ball).insert(Modifier::new(kind));
+            }
+            PowerUpKind::MultiBall => {
+                for angle in [-MULTI_BALL_SPREAD, MULTI_BALL_SPREAD] {
+                    commands.spawn((
+                        Ball,
+                        Position {
+                            coords: position.coords,
+                        },
+                        PreviousPosition(position.coords),
+                        Velocity {
+                            direction: Rot2::radians(angle) * velocity.direction,
+                        },
+                        *speed,
+                        *shape,
+                        *last_touched,
+                    ));
+                }
+            }
+        }
+    }
+}
+
+fn resize_factor(kind: PowerUpKind) -> f32 {
+    match kind {
+        PowerUpKind::Enlarge => ENLARGE_FACTOR,
+        PowerUpKind::Shrink => SHRINK_FACTOR,
+        PowerUpKind::MultiBall | PowerUpKind::SpeedBurst | PowerUpKind::Sticky => 1.,
+    }
+}
+
+/// A timed power-up, with the size and edge of the paddle holding it.
+type TimedModifier<'a> = (Entity, &'a mut Modifier, Option<(&'a mut Shape, &'a Edge)>);
+
+/// Undoes every timed power-up once it runs out.
+pub fn expire(
+    mut commands: Commands,
+    mut modifiers: Query<TimedModifier>,
+    tuning: Res<PaddleTuning>,
+    time: Res<Time>,
+) {
+    for (entity, mut modifier, paddle) in &mut modifiers {
+        if !modifier.timer.tick(time.delta()).finished() {
+            continue;
+        }
+
+        if let Some((mut shape, &edge)) = paddle {
+            if matches!(modifier.kind, PowerUpKind::Enlarge | PowerUpKind::Shrink) {
+                shape.size = Modifier::paddle_size(None, edge, &tuning);
+            }
+        }
+
+        commands.entity(entity).remove::<Modifier>();
+    }
+}
+
+/// A ball with the paddle that last touched it.
+type TouchedBall<'a> = (Entity, &'a Position, &'a mut Velocity, Ref<'a, LastTouched>);
+
+/// Balls a paddle just touched that are not held yet.
+type JustTouched = (With<Ball>, Changed<LastTouched>, Without<Stuck>);
+
+/// A paddle with the power-up that may make it sticky.
+type ModifiedPaddle<'a> = (&'a Position, &'a Modifier);
+
+/// Catches every ball that was just hit by a sticky paddle.
+pub fn stick(
+    mut commands: Commands,
+    mut balls: Query<TouchedBall, JustTouched>,
+    paddles: Query<ModifiedPaddle, (With<Paddle>, Without<Ball>)>,
+) {
+    for (ball, position, mut velocity, last_touched) in &mut balls {
+        // Balls split off by a multi-ball start out touched by the same paddle
+        if last_touched.is_added() {
+            continue;
+        }
+        let Some(paddle) = last_touched.0 else {
+            continue;
+        };
+        let Ok((paddle_position, modifier)) = paddles.get(paddle) else {
+            continue;
+        };
+        if modifier.kind != PowerUpKind::Sticky {
+            continue;
+        }
+
+        commands.entity(ball).insert(Stuck {
+            paddle,
+            offset: position.coords - paddle_position.coords,
+            direction: velocity.direction,
+            timer: Timer::new(STICK_TIME, TimerMode::Once),
+        });
+        velocity.direction = Vec2::ZERO;
+    }
+}
+
+/// Moves caught balls along with their paddle, and releases them once the
+/// paddle has held on long enough.
+pub fn carry(
+    mut commands: Commands,
+    mut balls: Query<(Entity, &mut Position, &mut Velocity, &mut Stuck), With<Ball>>,
+    paddles: Query<&Position, (With<Paddle>, Without<Ball>)>,
+    time: Res<Time>,
+) {
+    for (ball, mut position, mut velocity, mut stuck) in &mut balls {
+        if let Ok(paddle) = paddles.get(stuck.paddle) {
+            position.coords = paddle.coords + stuck.offset;
+        }
+
+        if stuck.timer.tick(time.delta()).finished() {
+            velocity.direction = stuck.direction;
+            commands.entity(ball).remove::<Stuck>();
+        }
+    }
+}
+
+/// Lets go of caught balls once the rally is over, so the ball that is put
+/// back in the center stays there.
+pub fn release_after_rally(
+    mut commands: Commands,
+    mut ended: EventReader<RallyEnded>,
+    stuck: Query<Entity, With<Stuck>>,
+) {
+    if ended.is_empty() {
+        return;
+    }
+    ended.clear();
+
+    for ball in &stuck {
+        commands.entity(ball).remove::<Stuck>();
+    }
+}
+
+/// Clears the field and undoes every power-up before a new match.
+pub fn reset(
+    mut commands: Commands,
+    power_ups: Query<Entity, With<PowerUp>>,
+    modified: Query<Entity, With<Modifier>>,
+    stuck: Query<Entity, With<Stuck>>,
+    mut paddles: Query<(&mut Shape, &Edge), With<Paddle>>,
+    mut spawner: ResMut<PowerUpSpawner>,
+    tuning: Res<PaddleTuning>,
+) {
+    for entity in &power_ups {
+        commands.entity(entity).despawn();
+    }
+    for entity in &modified {
+        commands.entity(entity).remove::<Modifier>();
+    }
+    for entity in &stuck {
+        commands.entity(entity).remove::<Stuck>();
+    }
+    for (mut shape, &edge) in &mut paddles {
+        shape.size = Modifier::paddle_size(None, edge, &tuning);
+    }
+    spawner.0.reset();
+}
+
+/// Gives every new power-up a square in its kind's color.
+pub fn add_meshes(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    colors: ArenaColors,
+    power_ups: Query<(Entity, &Shape, &PowerUp), Added<PowerUp>>,
+) {
+    let colors = colors.get();
+    for (entity, shape, power_up) in &power_ups {
+        let mesh = meshes.add(Rectangle::from_size(shape.size));
+        let material = materials.add(colors.power_up(power_up.0));
+        commands
+            .entity(entity)
+            .insert((Mesh2d(mesh), MeshMaterial2d(material)));
+    }
+}
+
+/// Repaints every power-up whenever the tuning or the palette changes.
+pub fn recolor(
+    colors: ArenaColors,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    power_ups: Query<(&PowerUp, &MeshMaterial2d<ColorMaterial>)>,
+) {
+    if !colors.is_changed() {
+        return;
+    }
+
+    let colors = colors.get();
+    for (power_up, material) in &power_ups {
+        if let Some(material) = materials.get_mut(material) {
+            material.color = colors.power_up(power_up.0);
+        }
+    }
+}
@@ -0,0 +1,460 @@
+//! Practice, returning shots fired by a launcher instead of playing an
+//! opponent.
+//!
+//! In `GameMode::Practice` the player's paddle guards the right edge and the
+//! other three are walled off. A launcher in the left wall fires the shots of
+//! the chosen `Drill` one after another, each from its own height, at its own
+//! angle and speed, after its own delay. Every shot is a rally of its own: it
+//! is served through the generalized `Serve`, so the ball waits at the
+//! launcher for the shot's delay and leaves at the shot's speed.
+//!
+//! A shot is returned when the player's paddle hits it. Returned shots are
+//! caught by the launcher's wall, unless the drill is played against the wall,
+//! in which case they bounce back and the rally goes on with every hit counting
+//! as another return. A shot that gets past the paddle is a miss. The score
+//! keeps the returns and misses of the drill being played, and once its last
+//! shot is over they are added to the `DrillRecords` kept for every drill.
+//!
+//! Drills are read from `assets/drills.ron`, which the game ships with built in
+//! for headless games; when drawn to a window it loads the file again, and
+//! natively it watches it for changes. Drills with shots the launcher cannot
+//! fire are left out with a warning.
+
+use std::{collections::BTreeMap, fmt, time::Duration};
+
+use bevy::{
+    asset::Asset,
+    ecs::{
+        event::{EventReader, EventWriter},
+        query::With,
+        resource::Resource,
+        system::{Query, Res, ResMut},
+    },
+    log::{info, warn},
+    math::Vec2,
+    reflect::TypePath,
+    state::state::NextState,
+    time::{Timer, TimerMode},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arena::Arena,
+    components::{Edge, Gutter, Player},
+    game::{AppState, Countdown, GameMode, MatchState, MatchWon, RallyEnded, Score, Scorer, Serve},
+    physics::{BallCollided, CollisionKind},
+    replay::Playback,
+    ron_asset::{Reloaded, RonAsset},
+    settings::Settings,
+    storage,
+    tuning::PaddleTuning,
+};
+
+/// Where the drills are loaded from, in the assets folder.
+pub const PATH: &str = "drills.ron";
+
+const RECORDS: &str = "practice";
+
+/// Steepest a shot can be fired at, in degrees either way from straight
+/// across the arena.
+pub const MAX_ANGLE: f32 = 70.;
+
+/// How the launcher fires one shot.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Shot {
+    /// Height along the launcher's wall the shot is fired from, from the
+    /// middle.
+    pub height: f32,
+    /// Degrees above straight across the arena, or below when negative.
+    pub angle: f32,
+    /// Units per second.
+    pub speed: f32,
+    /// Seconds the launcher waits before firing, once the last shot is over.
+    pub delay: f32,
+}
+
+/// Shots to return, fired in order.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Drill {
+    pub name: String,
+    /// Whether returned shots bounce back off the launcher's wall rather than
+    /// being caught.
+    #[serde(default)]
+    pub wall: bool,
+    pub shots: Vec<Shot>,
+}
+
+impl Default for Drill {
+    /// The first drill the game ships with.
+    fn default() -> Self {
+        Drills::default().0.remove(0)
+    }
+}
+
+/// Why a drill cannot be played.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DrillError {
+    NoShots,
+    /// The shot is fired from beyond the ends of the wall.
+    OffWall(usize),
+    TooSteep(usize),
+    NotMoving(usize),
+    NegativeDelay(usize),
+}
+
+impl fmt::Display for DrillError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoShots => write!(f, "there are no shots to fire"),
+            Self::OffWall(index) => write!(f, "shot {index} is fired from off the wall"),
+            Self::TooSteep(index) => write!(f, "shot {index} is steeper than {MAX_ANGLE}°"),
+            Self::NotMoving(index) => write!(f, "shot {index} does not move"),
+            Self::NegativeDelay(index) => write!(f, "shot {index} is fired before it is due"),
+        }
+    }
+}
+
+impl Drill {
+    pub fn validate(&self, arena: &Arena, tuning: &PaddleTuning) -> Result<(), DrillError> {
+        if self.shots.is_empty() {
+            return Err(DrillError::NoShots);
+        }
+        let reach = arena.half_height() - tuning.gutter_height - tuning.ball_size;
+        for (index, shot) in self.shots.iter().enumerate() {
+            if shot.height.abs() > reach {
+                return Err(DrillError::OffWall(index));
+            }
+            if shot.angle.abs() > MAX_ANGLE {
+                return Err(DrillError::TooSteep(index));
+            }
+            if shot.speed <= 0. {
+                return Err(DrillError::NotMoving(index));
+            }
+            if shot.delay < 0. {
+                return Err(DrillError::NegativeDelay(index));
+            }
+        }
+        Ok(())
+    }
+
+    /// How the launcher serves shot `index`, if the drill has that many.
+    pub fn serve(&self, index: usize, arena: &Arena, tuning: &PaddleTuning) -> Option<Serve> {
+        let shot = self.shots.get(index)?;
+        // The ball waits just clear of the wall
+        let x = -arena.half_width() + tuning.gutter_height + tuning.ball_size * 2.;
+        Some(Serve {
+            from: Vec2::new(x, shot.height),
+            direction: Vec2::from_angle(shot.angle.to_radians()),
+            speed: Some(shot.speed),
+        })
+    }
+}
+
+/// Every drill that can be picked.
+#[derive(Asset, Resource, TypePath, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Drills(pub Vec<Drill>);
+
+impl Default for Drills {
+    /// The drills the game ships with.
+    fn default() -> Self {
+        ron::from_str(include_str!("../assets/drills.ron")).expect("the shipped drills parse")
+    }
+}
+
+impl Drills {
+    pub fn get(&self, name: &str) -> Option<&Drill> {
+        self.0.iter().find(|drill| drill.name == name)
+    }
+
+    /// The drill picked after `name`, back to the first after the last.
+    pub fn after(&self, name: &str) -> &Drill {
+        let next = self
+            .0
+            .iter()
+            .position(|drill| drill.name == name)
+            .map_or(0, |index| index + 1);
+        &self.0[next % self.0.len()]
+    }
+}
+
+impl RonAsset for Drills {
+    const PATH: &'static str = PATH;
+}
+
+/// Offers the drills that can be played from the file whenever it is loaded
+/// or changes, keeping the ones before if none of them can.
+pub fn apply(
+    mut file: Reloaded<Drills>,
+    mut drills: ResMut<Drills>,
+    arena: Res<Arena>,
+    tuning: Res<PaddleTuning>,
+) {
+    let Some(loaded) = file.get() else {
+        return;
+    };
+
+    let mut valid = Vec::new();
+    for drill in &loaded.0 {
+        match drill.validate(&arena, &tuning) {
+            Ok(()) => valid.push(drill.clone()),
+            Err(error) => warn!("Left out the {} drill: {error}", drill.name),
+        }
+    }
+    if valid.is_empty() {
+        warn!("No drill in {PATH} can be played");
+        return;
+    }
+    info!("Loaded {} drills from {PATH}", valid.len());
+    *drills = Drills(valid);
+}
+
+/// How far into the drill practice is.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DrillProgress {
+    /// Index of the shot being played.
+    pub shot: usize,
+    /// Whether the player has hit the shot back yet.
+    pub returned: bool,
+}
+
+/// Returns and misses over every time a drill was played.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DrillRecord {
+    pub returns: usize,
+    pub misses: usize,
+}
+
+/// The record of every drill played, by name.
+#[derive(Resource, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DrillRecords(pub BTreeMap<String, DrillRecord>);
+
+impl DrillRecords {
+    pub fn load() -> Option<Self> {
+        storage::load(RECORDS)
+    }
+}
+
+/// Returns are scored for the player, and misses for the launcher across the
+/// arena.
+const RETURNS: Scorer = Scorer::PLAYER;
+const MISSES: Scorer = Scorer::OPPONENT;
+
+pub fn enabled(mode: Res<GameMode>) -> bool {
+    *mode == GameMode::Practice
+}
+
+/// Picks the drill in the settings for the next session, unless a replay is
+/// played back with the drill it was recorded with. Games without drills to
+/// pick from, such as headless ones, keep the one they were given.
+pub fn choose(
+    mut drill: ResMut<Drill>,
+    drills: Option<Res<Drills>>,
+    settings: Res<Settings>,
+    playback: Option<Res<Playback>>,
+) {
+    let Some(drills) = drills else {
+        return;
+    };
+    if playback.is_some() {
+        return;
+    }
+    if let Some(chosen) = drills.get(&settings.drill).or(drills.0.first()) {
+        *drill = chosen.clone();
+    }
+}
+
+/// Starts the drill over from its first shot, which the first serve of the
+/// match fires.
+pub fn start(
+    mut progress: ResMut<DrillProgress>,
+    mut serve: ResMut<Serve>,
+    drill: Res<Drill>,
+    mode: Res<GameMode>,
+    arena: Res<Arena>,
+    tuning: Res<PaddleTuning>,
+) {
+    *progress = DrillProgress::default();
+    if *mode != GameMode::Practice {
+        return;
+    }
+    if let Some(first) = drill.serve(0, &arena, &tuning) {
+        *serve = first;
+    }
+}
+
+/// Holds the serve for as long as the shot's delay.
+pub fn time_shot(
+    mut countdown: ResMut<Countdown>,
+    drill: Res<Drill>,
+    progress: Res<DrillProgress>,
+) {
+    if let Some(shot) = drill.shots.get(progress.shot) {
+        let delay = Duration::from_secs_f32(shot.delay.max(0.));
+        countdown.0 = Timer::new(delay, TimerMode::Once);
+    }
+}
+
+/// Scores a return for every hit off the player's paddle, and has the
+/// launcher's wall catch returned shots unless the drill is played against the
+/// wall.
+pub fn count_returns(
+    mut collided: EventReader<BallCollided>,
+    player: Query<(), With<Player>>,
+    gutters: Query<&Gutter>,
+    drill: Res<Drill>,
+    mut progress: ResMut<DrillProgress>,
+    mut score: ResMut<Score>,
+    mut ended: EventWriter<RallyEnded>,
+) {
+    for collided in collided.read() {
+        if collided.kind == CollisionKind::Paddle && player.contains(collided.wall) {
+            score.points[RETURNS.index()] += 1;
+            progress.returned = true;
+        } else if progress.returned
+            && !drill.wall
+            && gutters
+                .get(collided.wall)
+                .is_ok_and(|gutter| gutter.0 == Edge::Left)
+        {
+            ended.write(RallyEnded { by: RETURNS });
+            // The ball is back at the launcher, whatever else it hits
+            return;
+        }
+    }
+}
+
+/// Moves on to the next shot once a rally is over, serving it from the
+/// launcher.
+pub fn next_shot(
+    mut ended: EventReader<RallyEnded>,
+    mut progress: ResMut<DrillProgress>,
+    mut serve: ResMut<Serve>,
+    drill: Res<Drill>,
+    arena: Res<Arena>,
+    tuning: Res<PaddleTuning>,
+) {
+    for _ in ended.read() {
+        progress.shot += 1;
+        progress.returned = false;
+        if let Some(next) = drill.serve(progress.shot, &arena, &tuning) {
+            *serve = next;
+        }
+    }
+}
+
+/// Counts a miss for every shot that got past the paddle, and ends the
+/// session after the drill's last shot. The next shot is fired straight away,
+/// after its own delay rather than a pause for the point.
+pub fn end_rally(
+    mut ended: EventReader<RallyEnded>,
+    drill: Res<Drill>,
+    progress: Res<DrillProgress>,
+    mut score: ResMut<Score>,
+    mut won: EventWriter<MatchWon>,
+    mut next_state: ResMut<NextState<MatchState>>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+) {
+    for ended in ended.read() {
+        if ended.by != RETURNS {
+            score.points[MISSES.index()] += 1;
+        }
+        if progress.shot >= drill.shots.len() {
+            won.write(MatchWon { by: RETURNS });
+            next_app_state.set(AppState::GameOver);
+        } else {
+            next_state.set(MatchState::Serving);
+        }
+    }
+}
+
+/// Adds the returns and misses of every finished drill to its record, and
+/// saves the records.
+pub fn record(
+    mut records: ResMut<DrillRecords>,
+    mut won: EventReader<MatchWon>,
+    drill: Res<Drill>,
+    score: Res<Score>,
+    mode: Res<GameMode>,
+) {
+    if won.is_empty() {
+        return;
+    }
+    won.clear();
+    if *mode != GameMode::Practice {
+        return;
+    }
+
+    let record = records.0.entry(drill.name.clone()).or_default();
+    record.returns += score.points(RETURNS);
+    record.misses += score.points(MISSES);
+    storage::save(RECORDS, &*records);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shot(height: f32, angle: f32) -> Shot {
+        Shot {
+            height,
+            angle,
+            speed: 400.,
+            delay: 1.,
+        }
+    }
+
+    fn drill(shots: Vec<Shot>) -> Drill {
+        Drill {
+            name: "Test".into(),
+            wall: false,
+            shots,
+        }
+    }
+
+    #[test]
+    fn shipped_drills_can_be_played() {
+        let drills = Drills::default();
+
+        assert!(!drills.0.is_empty());
+        for drill in &drills.0 {
+            assert_eq!(
+                drill.validate(&Arena::default(), &PaddleTuning::default()),
+                Ok(()),
+                "{}",
+                drill.name
+            );
+        }
+    }
+
+    #[test]
+    fn shots_are_served_from_the_launcher() {
+        let arena = Arena::default();
+        let tuning = PaddleTuning::default();
+        let drill = drill(vec![shot(100., 0.), shot(-50., 45.)]);
+
+        let first = drill.serve(0, &arena, &tuning).unwrap();
+        assert!(first.from.x < -arena.half_width() + tuning.gutter_height * 2. + 50.);
+        assert_eq!(first.from.y, 100.);
+        assert_eq!(first.direction, Vec2::X);
+        assert_eq!(first.speed, Some(400.));
+
+        let second = drill.serve(1, &arena, &tuning).unwrap();
+        assert!((second.direction - Vec2::new(1., 1.).normalize()).length() < 1e-6);
+        assert!(drill.serve(2, &arena, &tuning).is_none());
+    }
+
+    #[test]
+    fn drills_have_to_be_fired_from_the_wall() {
+        let arena = Arena::default();
+        let tuning = PaddleTuning::default();
+        let validate = |shots| drill(shots).validate(&arena, &tuning);
+
+        assert_eq!(validate(vec![]), Err(DrillError::NoShots));
+        assert_eq!(
+            validate(vec![shot(0., 0.), shot(arena.half_height(), 0.)]),
+            Err(DrillError::OffWall(1))
+        );
+        assert_eq!(validate(vec![shot(0., -80.)]), Err(DrillError::TooSteep(0)));
+    }
+}
@@ -0,0 +1,305 @@
+//! Recording and replaying matches.
+//!
+//! The simulation only depends on how the match was set up, the seed of the
+//! `GameRng` and which movement actions were held on each paddle on every
+//! fixed tick. A `Replay` stores exactly that, so a match can be played out
+//! again to the same score:
+//! - Every match is recorded into the `Recording` resource, and saved through
+//!   the `storage` module once it is over or abandoned
+//! - A `Replay` handed to the `PaddlePlugin` starts straight into its match
+//!   and writes the recorded actions into each paddle's `ActionState` right
+//!   before `input::handle` reads them, so playback takes the same path through
+//!   the simulation as live input
+//!
+//! Both happen per fixed tick rather than per frame, so a replay plays out the
+//! same at any frame rate. The tuning and the breakout levels are stored with
+//! the match too, since both can be edited between recording and playback,
+//! and are not reloaded from their files while a replay plays.
+
+use bevy::ecs::{
+    query::{With, Without},
+    resource::Resource,
+    system::{Commands, Query, Res, ResMut, SystemParam},
+};
+use leafwing_input_manager::prelude::ActionState;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ai::Difficulty,
+    arena::Arena,
+    breakout::Levels,
+    components::{Opponent, Player},
+    game::{GameMode, GameRng, MatchRules},
+    input::{self, Action},
+    layout::Layout,
+    practice::Drill,
+    storage,
+    tuning::PaddleTuning,
+};
+
+const REPLAY: &str = "replay";
+
+/// Everything needed to play a match out again.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    pub arena: Arena,
+    pub mode: GameMode,
+    pub difficulty: Difficulty,
+    pub rules: MatchRules,
+    /// Replays recorded before layouts were added were played in the open
+    /// arena.
+    #[serde(default)]
+    pub layout: Layout,
+    /// The drill a practice session ran, and nothing for other modes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drill: Option<Drill>,
+    /// Replays recorded before the tuning was stored were played with the
+    /// default one.
+    #[serde(default)]
+    pub tuning: PaddleTuning,
+    /// The levels a breakout game was played through, and nothing for other
+    /// modes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub levels: Option<Levels>,
+    pub seed: u64,
+    /// Movement held on each paddle, tick by tick.
+    pub inputs: Vec<InputRun>,
+}
+
+/// A number of consecutive ticks with the same movement held on the player's
+/// and the opponent's paddle, in that order. Movement is packed into bits as
+/// `input::pack` does, 1 for up and 2 for down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputRun(pub u32, pub u8, pub u8);
+
+impl Replay {
+    /// Loads the last match that was saved.
+    pub fn load() -> Option<Self> {
+        storage::load(REPLAY)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, ron::error::SpannedError> {
+        ron::from_str(text)
+    }
+
+    /// Number of ticks the recorded inputs cover.
+    pub fn ticks(&self) -> usize {
+        self.inputs.iter().map(|run| run.0 as usize).sum()
+    }
+
+    fn push(&mut self, player: u8, opponent: u8) {
+        match self.inputs.last_mut() {
+            Some(run) if run.1 == player && run.2 == opponent => run.0 += 1,
+            _ => self.inputs.push(InputRun(1, player, opponent)),
+        }
+    }
+}
+
+/// The match being recorded, if one has started.
+#[derive(Resource, Debug, Default)]
+pub struct Recording(Option<Replay>);
+
+impl Recording {
+    pub fn replay(&self) -> Option<&Replay> {
+        self.0.as_ref()
+    }
+}
+
+/// A replay being played back, and how far into it the match is.
+#[derive(Resource, Debug)]
+pub struct Playback {
+    replay: Replay,
+    run: usize,
+    tick: u32,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            run: 0,
+            tick: 0,
+        }
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// Movement held on each paddle for the next tick, if the replay has not
+    /// run out.
+    fn next(&mut self) -> Option<(u8, u8)> {
+        let run = self.replay.inputs.get(self.run)?;
+        self.tick += 1;
+        if self.tick >= run.0 {
+            self.run += 1;
+            self.tick = 0;
+        }
+        Some((run.1, run.2))
+    }
+}
+
+/// Everything a match is set up with, which its replay starts from.
+#[derive(SystemParam)]
+pub struct MatchSetup<'w> {
+    arena: Res<'w, Arena>,
+    mode: Res<'w, GameMode>,
+    difficulty: Res<'w, Difficulty>,
+    rules: Res<'w, MatchRules>,
+    layout: Res<'w, Layout>,
+    drill: Res<'w, Drill>,
+    tuning: Res<'w, PaddleTuning>,
+    levels: Res<'w, Levels>,
+}
+
+impl MatchSetup<'_> {
+    /// A replay of the match played with `seed`, before any input.
+    fn replay(&self, seed: u64) -> Replay {
+        Replay {
+            arena: *self.arena,
+            mode: *self.mode,
+            difficulty: *self.difficulty,
+            rules: *self.rules,
+            layout: self.layout.clone(),
+            drill: (*self.mode == GameMode::Practice).then(|| self.drill.clone()),
+            tuning: self.tuning.clone(),
+            levels: (*self.mode == GameMode::Breakout).then(|| self.levels.clone()),
+            seed,
+            inputs: Vec::new(),
+        }
+    }
+}
+
+/// Starts recording a new match. Every match is given a fresh seed to record,
+/// unless a replay is being played back, in which case its seed is used.
+pub fn start_recording(
+    mut recording: ResMut<Recording>,
+    mut rng: ResMut<GameRng>,
+    playback: Option<Res<Playback>>,
+    setup: MatchSetup,
+) {
+    let seed = match playback {
+        Some(playback) => playback.replay.seed,
+        None => rng.next_u64(),
+    };
+    *rng = GameRng::from_seed(seed);
+
+    recording.0 = Some(setup.replay(seed));
+}
+
+/// Holds each paddle's recorded movement for this tick.
+pub fn play(
+    mut commands: Commands,
+    playback: Option<ResMut<Playback>>,
+    mut player: Query<&mut ActionState<Action>, With<Player>>,
+    mut opponent: Query<&mut ActionState<Action>, (With<Opponent>, Without<Player>)>,
+) {
+    let Some(mut playback) = playback else {
+        return;
+    };
+
+    let Some((player_held, opponent_held)) = playback.next() else {
+        // Let go of everything and hand the paddles back to live input
+        for mut action in player.iter_mut().chain(opponent.iter_mut()) {
+            input::hold(&mut action, 0);
+        }
+        commands.remove_resource::<Playback>();
+        return;
+    };
+
+    for mut action in &mut player {
+        input::hold(&mut action, player_held);
+    }
+    for mut action in &mut opponent {
+        input::hold(&mut action, opponent_held);
+    }
+}
+
+/// Adds the movement held on each paddle this tick to the recording.
+pub fn record(
+    mut recording: ResMut<Recording>,
+    player: Query<&ActionState<Action>, With<Player>>,
+    opponent: Query<&ActionState<Action>, With<Opponent>>,
+) {
+    let Some(replay) = recording.0.as_mut() else {
+        return;
+    };
+
+    let player = player.iter().next().map_or(0, input::pack);
+    let opponent = opponent.iter().next().map_or(0, input::pack);
+    replay.push(player, opponent);
+}
+
+/// A replay only covers a single match.
+pub fn stop_playback(mut commands: Commands) {
+    commands.remove_resource::<Playback>();
+}
+
+/// Saves the recorded match, so the last one played can always be replayed.
+pub fn save(recording: Res<Recording>) {
+    if let Some(replay) = recording
+        .replay()
+        .filter(|replay| !replay.inputs.is_empty())
+    {
+        storage::save(REPLAY, replay);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{MOVE_DOWN, MOVE_UP};
+
+    fn replay(inputs: Vec<InputRun>) -> Replay {
+        Replay {
+            arena: Arena::default(),
+            mode: GameMode::LocalVersus,
+            difficulty: Difficulty::default(),
+            rules: MatchRules::default(),
+            layout: Layout::default(),
+            drill: None,
+            tuning: PaddleTuning::default(),
+            levels: None,
+            seed: 0,
+            inputs,
+        }
+    }
+
+    #[test]
+    fn identical_ticks_are_merged_into_runs() {
+        let mut recorded = replay(Vec::new());
+        for (player, opponent) in [
+            (0, 0),
+            (0, 0),
+            (MOVE_UP, 0),
+            (MOVE_UP, 0),
+            (MOVE_UP, MOVE_DOWN),
+        ] {
+            recorded.push(player, opponent);
+        }
+
+        assert_eq!(
+            recorded.inputs,
+            vec![
+                InputRun(2, 0, 0),
+                InputRun(2, MOVE_UP, 0),
+                InputRun(1, MOVE_UP, MOVE_DOWN)
+            ]
+        );
+        assert_eq!(recorded.ticks(), 5);
+    }
+
+    #[test]
+    fn playback_yields_every_recorded_tick() {
+        let mut playback = Playback::new(replay(vec![
+            InputRun(2, MOVE_DOWN, 0),
+            InputRun(1, 0, MOVE_UP),
+        ]));
+
+        assert_eq!(playback.next(), Some((MOVE_DOWN, 0)));
+        assert_eq!(playback.next(), Some((MOVE_DOWN, 0)));
+        assert_eq!(playback.next(), Some((0, MOVE_UP)));
+        assert_eq!(playback.next(), None);
+    }
+}
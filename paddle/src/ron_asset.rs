@@ -0,0 +1,102 @@
+//! Game data loaded from RON files in the assets folder.
+//!
+//! The tuning, the arena layouts, the breakout levels and the practice drills
+//! are each read from a file of their own when the game is drawn to a window,
+//! and natively the files are watched so changes are picked up while the game
+//! is running. They all load the same way:
+//! - A type implementing `RonAsset` names its file, which is parsed by a
+//!   `RonLoader` for it
+//! - `load` starts loading the file and keeps it loaded in an `AssetFile`
+//! - A system taking `Reloaded` gets the file's contents every time it is
+//!   loaded or changes, to check them and copy them into the resource the game
+//!   plays with
+
+use std::marker::PhantomData;
+
+use bevy::{
+    asset::{io::Reader, Asset, AssetEvent, AssetLoader, AssetServer, Assets, Handle, LoadContext},
+    ecs::{
+        event::EventReader,
+        resource::Resource,
+        system::{Commands, Res, SystemParam},
+    },
+    reflect::TypePath,
+};
+use serde::de::DeserializeOwned;
+
+/// Game data read from a RON file.
+pub trait RonAsset: Asset + DeserializeOwned {
+    /// Where the file is loaded from, in the assets folder.
+    const PATH: &'static str;
+}
+
+/// Parses the file of a `RonAsset`. Every `RonLoader` takes `.ron` files, so
+/// the extension alone does not say which one a file is for: `load` asks for
+/// the file by the type of its asset, which picks the loader for that type.
+#[derive(TypePath)]
+pub struct RonLoader<A: RonAsset>(PhantomData<fn() -> A>);
+
+impl<A: RonAsset> Default for RonLoader<A> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<A: RonAsset> AssetLoader for RonLoader<A> {
+    type Asset = A;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ron"]
+    }
+}
+
+/// Keeps the file of a `RonAsset` loaded, and watched for changes.
+#[derive(Resource)]
+pub struct AssetFile<A: Asset>(pub Handle<A>);
+
+pub fn load<A: RonAsset>(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(AssetFile::<A>(asset_server.load(A::PATH)));
+}
+
+/// The contents of a `RonAsset`'s file, whenever it is loaded or changes.
+#[derive(SystemParam)]
+pub struct Reloaded<'w, 's, A: Asset> {
+    events: EventReader<'w, 's, AssetEvent<A>>,
+    assets: Res<'w, Assets<A>>,
+    file: Res<'w, AssetFile<A>>,
+}
+
+impl<A: Asset> Reloaded<'_, '_, A> {
+    /// The file's contents if they were loaded or changed since the last time
+    /// the system ran.
+    pub fn get(&mut self) -> Option<&A> {
+        let id = self.file.0.id();
+        // Every event is read, so none of them are seen again next time
+        let reloaded = self
+            .events
+            .read()
+            .filter(|event| {
+                matches!(
+                    **event,
+                    AssetEvent::LoadedWithDependencies { id: loaded }
+                        | AssetEvent::Modified { id: loaded } if loaded == id
+                )
+            })
+            .last()
+            .is_some();
+        reloaded.then(|| self.assets.get(id)).flatten()
+    }
+}
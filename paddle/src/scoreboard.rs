@@ -1,78 +1,213 @@
 //! Scoreboard module for displaying and updating game scores.
 //!
 //! This module handles the visual representation of the game score, including:
-//! - Separate text components for player and opponent scores
+//! - A text for each player's score, next to the edge they guard, shown for
+//!   the players the game mode has
 //! - Score display positioning and styling
 //! - Score update system that responds to score changes
+//! - A banner announcing the winner of the match and how to restart it
+//! - Text sized by the accessibility settings' text scale, resized as soon as
+//!   it changes
+//!
+//! In breakout the top corners show the lives left and the level being played
+//! instead, next to the bricks broken at the bottom. In practice they show
+//! the misses and returns of the drill.
 //!
 //! The scoreboard uses Bevy's UI system with absolute positioning and
 //! custom styling. It observes the Score resource and updates the display
-//! automatically when scores change.
+//! automatically when scores or the game mode change.
 
 use bevy::{
-    color::Color, ecs::{
+    color::Color,
+    ecs::{
         change_detection::DetectChanges,
         component::Component,
-        query::{With, Without},
+        event::EventReader,
+        query::{Has, Or, With},
         system::{Commands, Query, Res},
-    }, text::{JustifyText, TextColor, TextFont, TextLayout}, ui::{widget::Text, Node, PositionType, Val}, utils::default
+    },
+    text::{JustifyText, TextColor, TextFont, TextLayout},
+    ui::{widget::Text, Node, PositionType, Val},
+    utils::default,
+};
+
+use crate::{
+    accessibility::AccessibilitySettings,
+    breakout::{self, CurrentLevel, Lives},
+    components::{Edge, PlayerSide},
+    game::{GameMode, MatchRules, MatchWon, Score, Scorer},
+    settings::Settings,
 };
 
-use crate::game::Score;
+const SCORE_FONT_SIZE: f32 = 72.0;
+const BANNER_FONT_SIZE: f32 = 48.0;
 
+/// The score of one player.
 #[derive(Component)]
-pub struct PlayerScore;
+pub struct PlayerScore(pub Scorer);
 
 #[derive(Component)]
-pub struct OpponentScore;
+pub struct Banner;
 
 pub fn update(
-    mut player_score: Query<&mut Text, With<PlayerScore>>,
-    mut opponent_score: Query<&mut Text, (With<OpponentScore>, Without<PlayerScore>)>,
+    mut texts: Query<(&mut Text, &PlayerScore)>,
     score: Res<Score>,
+    rules: Res<MatchRules>,
+    mode: Res<GameMode>,
+    lives: Res<Lives>,
+    level: Res<CurrentLevel>,
 ) {
-    if score.is_changed() {
-        if let Ok(mut player_score) = player_score.get_single_mut() {
-            player_score.0 = score.player.to_string();
+    if score.is_changed() || mode.is_changed() || lives.is_changed() || level.is_changed() {
+        // Only show sets won when there is more than one to play
+        let display = |player: Scorer| {
+            let points = score.points(player);
+            if rules.best_of > 1 {
+                format!("{points} ({})", score.sets(player))
+            } else {
+                points.to_string()
+            }
+        };
+
+        for (mut text, &PlayerScore(player)) in &mut texts {
+            let edge = Edge::of(player);
+            text.0 = if *mode == GameMode::Breakout && edge == Edge::Left {
+                format!("Lives {}", lives.0)
+            } else if *mode == GameMode::Breakout && edge == Edge::Right {
+                format!("Level {}", level.0 + 1)
+            } else if *mode == GameMode::Practice && edge == Edge::Left {
+                format!("Misses {}", score.points(player))
+            } else if *mode == GameMode::Practice && edge == Edge::Right {
+                format!("Returns {}", score.points(player))
+            } else if mode.edges().contains(&edge) {
+                display(player)
+            } else {
+                String::new()
+            };
         }
+    }
+}
 
-        if let Ok(mut opponent_score) = opponent_score.get_single_mut() {
-            opponent_score.0 = score.opponent.to_string();
+pub fn announce_winner(
+    mut banner: Query<&mut Text, With<Banner>>,
+    mut won: EventReader<MatchWon>,
+    mode: Res<GameMode>,
+    score: Res<Score>,
+    settings: Res<Settings>,
+    side: PlayerSide,
+) {
+    for won in won.read() {
+        if let Ok(mut banner) = banner.single_mut() {
+            let winner = if *mode == GameMode::Practice {
+                format!(
+                    "Drill done: {} returned, {} missed",
+                    score.points(Scorer::PLAYER),
+                    score.points(Scorer::OPPONENT)
+                )
+            } else if *mode == GameMode::Breakout {
+                if won.by == breakout::player() {
+                    "You cleared every level!".into()
+                } else {
+                    "Game over".into()
+                }
+            } else if won.by == side.scorer() {
+                "You win!".into()
+            } else if *mode == GameMode::FourPlayer {
+                let edge = format!("{:?}", Edge::of(won.by)).to_lowercase();
+                format!("The {edge} paddle wins!")
+            } else {
+                "Opponent wins!".into()
+            };
+            let restart = settings.bindings.restart_prompt(*mode);
+            banner.0 = format!("{winner}\nPress {restart} to play again");
         }
     }
 }
 
-pub fn spawn(mut commands: Commands) {
-    commands.spawn((
-        PlayerScore,
-        Text::new("0"),
-        TextFont {
-            font_size: 72.0,
-            ..default()
-        },
-        TextColor(Color::WHITE),
-        TextLayout::new_with_justify(JustifyText::Center),
-        Node {
-            position_type: PositionType::Absolute,
-            top: Val::Px(5.0),
-            right: Val::Px(15.0),
-            ..default()
-        },
-    ));
+pub fn clear_banner(mut banner: Query<&mut Text, With<Banner>>) {
+    if let Ok(mut banner) = banner.single_mut() {
+        banner.0.clear();
+    }
+}
+
+/// Every text of the scoreboard: the scores and the banner.
+type ScoreboardText = Or<(With<PlayerScore>, With<Banner>)>;
+
+/// Scales every text of the scoreboard to the accessibility settings'
+/// `text_scale` whenever it changes.
+pub fn resize(
+    accessibility: Res<AccessibilitySettings>,
+    mut texts: Query<(&mut TextFont, Has<Banner>), ScoreboardText>,
+) {
+    if !accessibility.is_changed() {
+        return;
+    }
+
+    for (mut font, is_banner) in &mut texts {
+        let size = if is_banner {
+            BANNER_FONT_SIZE
+        } else {
+            SCORE_FONT_SIZE
+        };
+        font.font_size = size * accessibility.text_scale;
+    }
+}
+
+pub fn spawn(mut commands: Commands, accessibility: Res<AccessibilitySettings>) {
+    for edge in Edge::ALL {
+        // The side scores sit in the top corners, the top and bottom ones
+        // centered along their edge
+        let node = match edge {
+            Edge::Right => Node {
+                top: Val::Px(5.0),
+                right: Val::Px(15.0),
+                ..default()
+            },
+            Edge::Left => Node {
+                top: Val::Px(5.0),
+                left: Val::Px(15.0),
+                ..default()
+            },
+            Edge::Top => Node {
+                top: Val::Px(5.0),
+                width: Val::Percent(100.0),
+                ..default()
+            },
+            Edge::Bottom => Node {
+                bottom: Val::Px(5.0),
+                width: Val::Percent(100.0),
+                ..default()
+            },
+        };
+
+        commands.spawn((
+            PlayerScore(edge.scorer()),
+            Text::new("0"),
+            TextFont {
+                font_size: SCORE_FONT_SIZE * accessibility.text_scale,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+            TextLayout::new_with_justify(JustifyText::Center),
+            Node {
+                position_type: PositionType::Absolute,
+                ..node
+            },
+        ));
+    }
 
     commands.spawn((
-        OpponentScore,
-        Text::new("0"),
+        Banner,
+        Text::default(),
         TextFont {
-            font_size: 72.0,
+            font_size: BANNER_FONT_SIZE * accessibility.text_scale,
             ..default()
         },
         TextColor(Color::WHITE),
         TextLayout::new_with_justify(JustifyText::Center),
         Node {
             position_type: PositionType::Absolute,
-            top: Val::Px(5.0),
-            left: Val::Px(15.0),
+            top: Val::Percent(40.0),
+            width: Val::Percent(100.0),
             ..default()
         },
     ));
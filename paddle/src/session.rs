@@ -0,0 +1,414 @@
+//! Online versus, with rollback networking through GGRS.
+//!
+//! Two players find each other through a matchbox signalling server, set up the
+//! same way as in blockshot, and then each runs the simulation for both paddles:
+//! - Every frame, the movement held on the local paddle is packed into a byte
+//!   with `input::pack` and sent to the other player as its rollback input
+//! - The rally is stepped in `GgrsSchedule` from both players' inputs. When an
+//!   input arrives late and differs from what GGRS predicted, the last frames
+//!   are rolled back and stepped again
+//! - Everything the rally changes (positions, velocities, the ball's speed,
+//!   the score, the serve and its countdown) is registered for rollback
+//! - Both games step the same match, with player 0 on the left. Each player's
+//!   own paddle, the one the scoreboard and the statistics call theirs, is
+//!   moved to the side they were handed when the session starts
+//!
+//! `MatchState` is not rolled back, so online rallies do not go through it:
+//! the ball simply waits in the center for the serve delay after every point.
+//! Nothing that cannot be taken back happens in `GgrsSchedule` either:
+//! - The rally only notes who won the match and on which frame, in the rolled
+//!   back `MatchDecided`. The match ends in `Update`, once that frame is
+//!   confirmed and no late input can change it anymore
+//! - Collisions and points are remembered by the frame that sent them. When a
+//!   frame is stepped again after a rollback, only the ones new to it are
+//!   handed on to be played, so sounds and effects play once each, including
+//!   for a hit or a point that only a corrected input brought about
+//!
+//! Run `matchbox_server` locally, then start two games in online versus.
+
+use std::{collections::BTreeMap, time::Duration};
+
+use bevy::{
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::{Event, EventReader, EventWriter, Events},
+        query::{Has, With, Without},
+        resource::Resource,
+        system::{Commands, Query, Res, ResMut},
+    },
+    log::{info, warn},
+    math::Vec2,
+    state::state::NextState,
+    time::Time,
+};
+use bevy_ggrs::{
+    ggrs::{self},
+    AddRollbackCommandExtension, LocalInputs, LocalPlayers, PlayerInputs, Rollback,
+    RollbackFrameCount, RollbackFrameRate, Session,
+};
+use bevy_matchbox::{prelude::PeerId, MatchboxSocket};
+use leafwing_input_manager::prelude::ActionState;
+
+use crate::{
+    arena::Arena,
+    components::{paddle, Ball, Edge, Paddle, Player, Position, Velocity},
+    game::{
+        AppState, Countdown, MatchRules, MatchWon, PointScored, RallyEnded, Score, Scorer, Serve,
+    },
+    input::{self, Action, PointerTarget},
+    physics::BallCollided,
+    tuning::PaddleTuning,
+};
+
+const MAX_PLAYER_COUNT: usize = 2;
+
+// The first generic parameter, u8, is the input type: the movement bits from
+// `input::pack` fit easily in a single byte
+// The second parameter is the address type of peers: Matchbox' WebRtcSocket
+// addresses are called `PeerId`s
+pub type Config = bevy_ggrs::GgrsConfig<u8, PeerId>;
+
+/// Which player's inputs drive a paddle online: the left paddle belongs to
+/// player 0 and the right one to player 1.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OnlinePlayer(pub usize);
+
+impl OnlinePlayer {
+    pub fn of(edge: Edge) -> Self {
+        Self(if edge == Edge::Left { 0 } else { 1 })
+    }
+
+    pub fn edge(self) -> Edge {
+        if self.0 == 0 {
+            Edge::Left
+        } else {
+            Edge::Right
+        }
+    }
+}
+
+/// Who won the online match, and on which frame. Rolled back with the rest of
+/// the rally, so a win that was only predicted is taken back with it.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MatchDecided(pub Option<(Scorer, i32)>);
+
+/// How many of the latest frames `Played` remembers. GGRS never rolls back
+/// further than its prediction window, 8 frames unless told otherwise.
+const REMEMBERED_FRAMES: i32 = 32;
+
+/// The collisions or points each recent frame sent, so that stepping a frame
+/// again after a rollback only plays what is new in it.
+#[derive(Resource, Debug)]
+pub struct Played<T> {
+    frames: BTreeMap<i32, Vec<T>>,
+    /// New ones, waiting for every frame of this update to be stepped.
+    unplayed: Vec<T>,
+}
+
+impl<T> Default for Played<T> {
+    fn default() -> Self {
+        Self {
+            frames: BTreeMap::new(),
+            unplayed: Vec::new(),
+        }
+    }
+}
+
+impl<T: Clone + PartialEq> Played<T> {
+    /// Notes what stepping `frame` sent, and keeps whatever the earlier times
+    /// it was stepped did not send to be played.
+    fn step(&mut self, frame: i32, sent: impl IntoIterator<Item = T>) {
+        self.frames
+            .retain(|&played, _| played > frame - REMEMBERED_FRAMES);
+        let played = self.frames.entry(frame).or_default();
+        let mut earlier = played.clone();
+        for event in sent {
+            if let Some(index) = earlier.iter().position(|played| *played == event) {
+                earlier.swap_remove(index);
+            } else {
+                played.push(event.clone());
+                self.unplayed.push(event);
+            }
+        }
+    }
+}
+
+pub fn connect(mut commands: Commands) {
+    let room = "ws://127.0.0.1:3536/paddle?next=2";
+    info!("connecting to server: {room}");
+    commands.insert_resource(MatchboxSocket::new_unreliable(room));
+}
+
+pub fn wait_for_players(
+    mut commands: Commands,
+    mut socket: ResMut<MatchboxSocket>,
+    mut next_state: ResMut<NextState<AppState>>,
+    paddles: Query<(Entity, Has<Player>), With<Paddle>>,
+    arena: Res<Arena>,
+    tuning: Res<PaddleTuning>,
+) {
+    if socket.get_channel(0).is_err() {
+        return; // we assume the game has already started
+    }
+    // check for new connections
+    socket.update_peers();
+    let players = socket.players();
+
+    if players.len() < MAX_PLAYER_COUNT {
+        return; // we need more players to get started
+    }
+
+    info!("opponent found, starting the match");
+
+    let mut builder = ggrs::SessionBuilder::<Config>::new()
+        .with_num_players(MAX_PLAYER_COUNT)
+        .with_input_delay(2);
+
+    for (i, player) in players.into_iter().enumerate() {
+        builder = match builder.add_player(player, i) {
+            Ok(b) => b,
+            Err(e) => {
+                warn!("failed to add player to the session: {}", e);
+                return;
+            }
+        }
+    }
+
+    let chan = match socket.take_channel(0) {
+        Ok(chan) => chan,
+        Err(e) => {
+            info!("failed to take channel: {}", e);
+            return;
+        }
+    };
+
+    let session = match builder.start_p2p_session(chan) {
+        Ok(s) => s,
+        Err(e) => {
+            warn!("failed to start the session: {}", e);
+            return;
+        }
+    };
+
+    // Both games step the same match, where player 0 is on the left. The
+    // player's own paddle moves to the side they were handed, before the
+    // first frame is stepped and saved
+    let local = session.local_player_handles().first().copied().unwrap_or(1);
+    for (entity, is_player) in &paddles {
+        let player = OnlinePlayer(if is_player { local } else { 1 - local });
+        commands
+            .entity(entity)
+            .insert((paddle(player.edge(), &arena, &tuning), player));
+    }
+
+    commands.insert_resource(Session::P2P(session));
+    next_state.set(AppState::Playing);
+}
+
+/// Leaves the online match, whether it was won or given up for the menus.
+pub fn disconnect(mut commands: Commands) {
+    commands.remove_resource::<Session<Config>>();
+    commands.remove_resource::<MatchboxSocket>();
+    commands.insert_resource(MatchDecided::default());
+    commands.insert_resource(Played::<BallCollided>::default());
+    commands.insert_resource(Played::<PointScored>::default());
+}
+
+/// Entities with `T` not registered for rollback yet.
+type Unregistered<T> = (With<T>, Without<Rollback>);
+
+/// Registers the ball and the paddles for rollback, handing each paddle to the
+/// player whose inputs drive it on its side.
+pub fn setup_rollback(
+    mut commands: Commands,
+    balls: Query<Entity, Unregistered<Ball>>,
+    paddles: Query<(Entity, &Edge), Unregistered<Paddle>>,
+) {
+    for entity in &balls {
+        commands.entity(entity).add_rollback();
+    }
+
+    for (entity, &edge) in &paddles {
+        commands
+            .entity(entity)
+            .insert(OnlinePlayer::of(edge))
+            .add_rollback();
+    }
+}
+
+/// Sends the movement held on the local paddle as the input of every local
+/// player. Whichever side a player ends up on, they steer it with their own
+/// controls, and a touch or the mouse steers the paddle they are playing.
+pub fn read_inputs(
+    mut commands: Commands,
+    players: Res<LocalPlayers>,
+    controls: Query<(&ActionState<Action>, &PointerTarget), With<Player>>,
+    paddles: Query<(&Position, &OnlinePlayer)>,
+    tuning: Res<PaddleTuning>,
+    rate: Res<RollbackFrameRate>,
+) {
+    let controls = controls.iter().next();
+    // Online, paddles move once every rollback frame
+    let dead_zone = input::pointer_dead_zone(
+        tuning.paddle_speed,
+        Duration::from_secs_f64(1. / **rate as f64),
+    );
+    let local_inputs = players
+        .0
+        .iter()
+        .map(|&handle| {
+            let paddle = paddles.iter().find(|(_, player)| player.0 == handle);
+            let held = match (controls, paddle) {
+                (Some((_, PointerTarget(Some(target)))), Some((position, _))) => {
+                    input::toward(*target, position.coords.y, dead_zone)
+                }
+                (Some((action, _)), _) => input::pack(action),
+                (None, _) => 0,
+            };
+            (handle, held)
+        })
+        .collect();
+
+    commands.insert_resource(LocalInputs::<Config>(local_inputs));
+}
+
+/// Moves each paddle the way its player's input for this frame says.
+pub fn apply_inputs(
+    inputs: Res<PlayerInputs<Config>>,
+    mut paddles: Query<(&mut Velocity, &OnlinePlayer)>,
+) {
+    for (mut velocity, player) in &mut paddles {
+        let (held, _) = inputs[player.0];
+        velocity.direction.y = input::direction(held);
+    }
+}
+
+/// Launches the ball once it has waited out the serve delay, and winds the
+/// countdown back up for the next point.
+pub fn serve(
+    mut balls: Query<&mut Velocity, With<Ball>>,
+    mut countdown: ResMut<Countdown>,
+    serve: Res<Serve>,
+    decided: Res<MatchDecided>,
+    time: Res<Time>,
+) {
+    // Once the match is won, the ball waits for the win to be confirmed
+    if decided.0.is_some()
+        || balls
+            .iter()
+            .any(|velocity| velocity.direction != Vec2::ZERO)
+    {
+        return;
+    }
+
+    if countdown.0.tick(time.delta()).just_finished() {
+        for mut velocity in &mut balls {
+            velocity.direction = serve.direction;
+        }
+        countdown.0.reset();
+    }
+}
+
+/// Awards the set when a rally decides it, and notes who won the match and
+/// when if that set decided it.
+pub fn end_rally(
+    mut score: ResMut<Score>,
+    mut decided: ResMut<MatchDecided>,
+    rules: Res<MatchRules>,
+    frame: Res<RollbackFrameCount>,
+    mut ended: EventReader<RallyEnded>,
+) {
+    if ended.is_empty() {
+        return;
+    }
+    ended.clear();
+
+    if let Some(winner) = score.settle_set(&rules) {
+        decided.0 = Some((winner, frame.0));
+    }
+}
+
+/// Drops the events the last update played before a frame is stepped, so
+/// the rally only counts the ones its own frame sends.
+pub fn clear_played<T: Event>(mut events: ResMut<Events<T>>) {
+    events.clear();
+}
+
+/// Takes what a frame sent once the rally has counted it, keeping only what
+/// the frame did not send the earlier times it was stepped.
+pub fn remember_played<T: Event + Clone + PartialEq>(
+    frame: Res<RollbackFrameCount>,
+    mut events: ResMut<Events<T>>,
+    mut played: ResMut<Played<T>>,
+) {
+    played.step(frame.0, events.drain());
+}
+
+/// Sends what was new in the frames stepped this update again, for the
+/// sounds and effects to play.
+pub fn play<T: Event>(mut played: ResMut<Played<T>>, mut events: EventWriter<T>) {
+    events.write_batch(played.unplayed.drain(..));
+}
+
+/// Ends the match once the frame that decided it is confirmed, so no late
+/// input can take the win back anymore.
+pub fn finish(
+    decided: Res<MatchDecided>,
+    session: Option<Res<Session<Config>>>,
+    frame: Res<RollbackFrameCount>,
+    mut won: EventWriter<MatchWon>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let (Some((winner, decided_on)), Some(session)) = (decided.0, session) else {
+        return;
+    };
+    let confirmed = match &*session {
+        Session::P2P(session) => session.confirmed_frame(),
+        // Every input of a sync test is local, and spectators only ever step
+        // confirmed frames
+        Session::SyncTest(_) | Session::Spectator(_) => frame.0,
+    };
+    if decided_on > confirmed {
+        return;
+    }
+
+    won.write(MatchWon { by: winner });
+    next_state.set(AppState::GameOver);
+}
+
+/// Checksums positions bit for bit, so GGRS notices as soon as the two
+/// simulations drift apart.
+pub fn checksum_position(position: &Position) -> u64 {
+    let Vec2 { x, y } = position.coords;
+    (u64::from(x.to_bits()) << 32) | u64::from(y.to_bits())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stepping_a_frame_again_only_plays_what_is_new() {
+        let mut played = Played::default();
+        played.step(3, [1, 1, 2]);
+        assert_eq!(played.unplayed, [1, 1, 2]);
+        played.unplayed.clear();
+
+        // A corrected input added a second 2 and took a 1 away
+        played.step(3, [1, 2, 2]);
+        assert_eq!(played.unplayed, [2]);
+        // The same again on the frame after is new to that frame
+        played.step(4, [2]);
+        assert_eq!(played.unplayed, [2, 2]);
+    }
+
+    #[test]
+    fn old_frames_are_forgotten() {
+        let mut played = Played::default();
+        played.step(0, [1]);
+        played.step(REMEMBERED_FRAMES, [1]);
+
+        assert!(!played.frames.contains_key(&0));
+    }
+}
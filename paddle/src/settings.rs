@@ -0,0 +1,315 @@
+//! Settings and match history that survive a restart.
+//!
+//! This module provides:
+//! - The `Settings` resource with the player's key bindings, AI difficulty,
+//!   volume and mute, points to win, whether power-ups appear, which visual
+//!   effects are shown, the arena layout, the practice drill and the
+//!   accessibility options, applied to the game whenever it changes
+//! - The `MatchHistory` resource recording every finished match, along with
+//!   the high scores derived from it
+//! - Systems saving both through the `storage` module as soon as they change
+//!
+//! Both are loaded once when the `PaddlePlugin` is built. A headless game
+//! neither loads nor saves anything, so tests and bots start from defaults.
+
+use bevy::ecs::{
+    change_detection::{DetectChanges, DetectChangesMut},
+    event::EventReader,
+    resource::Resource,
+    system::{Res, ResMut},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    accessibility::AccessibilitySettings,
+    ai::Difficulty,
+    components::{Edge, PlayerSide},
+    effects::EffectsSettings,
+    game::{GameMode, MatchRules, MatchWon, Score, Scorer},
+    input::KeyBindings,
+    layout,
+    practice::Drill,
+    storage,
+};
+
+const SETTINGS: &str = "settings";
+const HISTORY: &str = "history";
+
+/// Number of most recent matches kept in the history.
+const MAX_HISTORY: usize = 100;
+
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub bindings: KeyBindings,
+    pub difficulty: Difficulty,
+    /// From 0 for muted to 1 for full volume.
+    pub volume: f32,
+    /// Silences the game without losing the volume it was set to.
+    pub muted: bool,
+    /// Points needed to win a set.
+    pub points_to_win: usize,
+    pub power_ups: bool,
+    pub effects: EffectsSettings,
+    /// Name of the arena layout matches are played in.
+    pub layout: String,
+    /// Name of the drill practice runs.
+    pub drill: String,
+    pub accessibility: AccessibilitySettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            bindings: KeyBindings::default(),
+            difficulty: Difficulty::default(),
+            volume: 0.8,
+            muted: false,
+            points_to_win: MatchRules::default().points_to_win,
+            power_ups: MatchRules::default().power_ups,
+            effects: EffectsSettings::default(),
+            layout: layout::OPEN.into(),
+            drill: Drill::default().name,
+            accessibility: AccessibilitySettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Loads the saved settings, if any were saved.
+    pub fn load() -> Option<Self> {
+        storage::load(SETTINGS)
+    }
+}
+
+/// How a finished match went, with the winner by the paddle they played. In
+/// four player, the opponent is the left paddle; online, the player may be on
+/// either side.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchRecord {
+    pub mode: GameMode,
+    pub difficulty: Difficulty,
+    pub winner: Scorer,
+    pub player_sets: usize,
+    pub opponent_sets: usize,
+    /// Points in the deciding set.
+    pub player_points: usize,
+    pub opponent_points: usize,
+}
+
+/// The most recent matches, oldest first.
+#[derive(Resource, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MatchHistory {
+    pub matches: Vec<MatchRecord>,
+}
+
+impl MatchHistory {
+    pub fn load() -> Option<Self> {
+        storage::load(HISTORY)
+    }
+
+    pub fn record(&mut self, record: MatchRecord) {
+        self.matches.push(record);
+        if self.matches.len() > MAX_HISTORY {
+            let excess = self.matches.len() - MAX_HISTORY;
+            self.matches.drain(..excess);
+        }
+    }
+
+    /// Single player matches won against the AI.
+    pub fn wins(&self) -> usize {
+        self.single_player()
+            .filter(|record| record.winner == Scorer::PLAYER)
+            .count()
+    }
+
+    /// Single player matches played against the AI.
+    pub fn played(&self) -> usize {
+        self.single_player().count()
+    }
+
+    /// The longest run of single player wins in a row, the game's high score.
+    pub fn best_streak(&self) -> usize {
+        let mut best = 0;
+        let mut streak = 0;
+        for record in self.single_player() {
+            if record.winner == Scorer::PLAYER {
+                streak += 1;
+                best = best.max(streak);
+            } else {
+                streak = 0;
+            }
+        }
+        best
+    }
+
+    fn single_player(&self) -> impl Iterator<Item = &MatchRecord> {
+        self.matches
+            .iter()
+            .filter(|record| record.mode == GameMode::SinglePlayer)
+    }
+}
+
+/// Applies the settings to the game whenever they, or the mode they are
+/// played in, change. The loaded settings were already applied when the
+/// plugin was built.
+pub fn apply(
+    settings: Res<Settings>,
+    mode: Res<GameMode>,
+    mut difficulty: ResMut<Difficulty>,
+    mut rules: ResMut<MatchRules>,
+    mut effects: ResMut<EffectsSettings>,
+    mut accessibility: ResMut<AccessibilitySettings>,
+) {
+    if !(settings.is_changed() || mode.is_changed()) || settings.is_added() {
+        return;
+    }
+
+    difficulty.set_if_neq(settings.difficulty);
+    effects.set_if_neq(settings.effects);
+    accessibility.set_if_neq(settings.accessibility);
+    rules.set_if_neq(MatchRules {
+        points_to_win: settings.points_to_win,
+        power_ups: settings.power_ups,
+        ball_speed: ball_speed(&settings, *mode),
+        ..*rules
+    });
+}
+
+/// How fast the ball is served in `mode`. Online matches are played at full
+/// speed, so both players' games agree on it.
+pub fn ball_speed(settings: &Settings, mode: GameMode) -> f32 {
+    if mode == GameMode::OnlineVersus {
+        1.
+    } else {
+        settings.accessibility.ball_speed
+    }
+}
+
+pub fn save(settings: Res<Settings>) {
+    // Nothing to save when the settings were just loaded
+    if settings.is_changed() && !settings.is_added() {
+        storage::save(SETTINGS, &*settings);
+    }
+}
+
+/// Adds every won match to the history and saves it. Finished practice
+/// sessions are not matches, and are left to the `practice` module.
+pub fn record_match(
+    mut history: ResMut<MatchHistory>,
+    mut won: EventReader<MatchWon>,
+    score: Res<Score>,
+    mode: Res<GameMode>,
+    difficulty: Res<Difficulty>,
+    side: PlayerSide,
+) {
+    if *mode == GameMode::Practice {
+        won.clear();
+    }
+    if won.is_empty() {
+        return;
+    }
+
+    // In breakout the player is numbered by the bottom edge they guard, and
+    // online they may play on the left
+    let player = side.edge();
+    let opponent = if player == Edge::Left {
+        Edge::Right
+    } else {
+        Edge::Left
+    };
+    for won in won.read() {
+        history.record(MatchRecord {
+            mode: *mode,
+            difficulty: *difficulty,
+            winner: won.by,
+            player_sets: score.sets(player.scorer()),
+            opponent_sets: score.sets(opponent.scorer()),
+            player_points: score.points(player.scorer()),
+            opponent_points: score.points(opponent.scorer()),
+        });
+    }
+    storage::save(HISTORY, &*history);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(mode: GameMode, winner: Scorer) -> MatchRecord {
+        MatchRecord {
+            mode,
+            difficulty: Difficulty::Medium,
+            winner,
+            player_sets: 1,
+            opponent_sets: 0,
+            player_points: 11,
+            opponent_points: 7,
+        }
+    }
+
+    #[test]
+    fn best_streak_only_counts_single_player() {
+        let mut history = MatchHistory::default();
+        for (mode, winner) in [
+            (GameMode::SinglePlayer, Scorer::PLAYER),
+            (GameMode::SinglePlayer, Scorer::PLAYER),
+            (GameMode::SinglePlayer, Scorer::OPPONENT),
+            (GameMode::SinglePlayer, Scorer::PLAYER),
+            (GameMode::LocalVersus, Scorer::OPPONENT),
+            (GameMode::SinglePlayer, Scorer::PLAYER),
+            (GameMode::SinglePlayer, Scorer::PLAYER),
+        ] {
+            history.record(record(mode, winner));
+        }
+
+        assert_eq!(history.best_streak(), 3);
+        assert_eq!(history.wins(), 5);
+        assert_eq!(history.played(), 6);
+    }
+
+    #[test]
+    fn history_keeps_the_most_recent_matches() {
+        let mut history = MatchHistory::default();
+        for _ in 0..MAX_HISTORY {
+            history.record(record(GameMode::SinglePlayer, Scorer::OPPONENT));
+        }
+        history.record(record(GameMode::SinglePlayer, Scorer::PLAYER));
+
+        assert_eq!(history.matches.len(), MAX_HISTORY);
+        assert_eq!(history.matches.last().unwrap().winner, Scorer::PLAYER);
+    }
+
+    #[test]
+    fn settings_round_trip_through_ron() {
+        let settings = Settings {
+            difficulty: Difficulty::Hard,
+            volume: 0.5,
+            points_to_win: 7,
+            ..Default::default()
+        };
+
+        let text = ron::to_string(&settings).unwrap();
+        assert_eq!(ron::from_str::<Settings>(&text).unwrap(), settings);
+    }
+
+    #[test]
+    fn missing_settings_fall_back_to_defaults() {
+        let settings: Settings = ron::from_str("(difficulty: Easy)").unwrap();
+
+        assert_eq!(settings.difficulty, Difficulty::Easy);
+        assert_eq!(settings.bindings, KeyBindings::default());
+        assert_eq!(settings.points_to_win, 11);
+        assert_eq!(settings.effects, EffectsSettings::ALL);
+    }
+
+    #[test]
+    fn online_matches_are_played_at_full_speed() {
+        let mut settings = Settings::default();
+        settings.accessibility.ball_speed = 0.7;
+
+        assert_eq!(ball_speed(&settings, GameMode::SinglePlayer), 0.7);
+        assert_eq!(ball_speed(&settings, GameMode::OnlineVersus), 1.);
+    }
+}
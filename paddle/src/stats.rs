@@ -0,0 +1,233 @@
+//! Statistics gathered over a match.
+//!
+//! The `MatchStats` resource counts paddle hits, rally lengths, the fastest the
+//! ball went and how many points each player wins on the serves they receive.
+//! It is updated from the `BallCollided` and `PointScored` events of every
+//! tick, cleared when a new match starts, shown on the game over screen and
+//! exported as `stats.json` through the `storage` module once a match is over.
+//! Headless games keep it up to date too, so bots and tests can compare how
+//! the AI and different tunings play.
+
+use bevy::ecs::{
+    event::EventReader,
+    query::With,
+    resource::Resource,
+    system::{Query, Res, ResMut},
+};
+use serde::Serialize;
+
+use crate::{
+    components::{Edge, Paddle},
+    game::{GameMode, PointScored, RallyEnded, Scorer, Serve, MAX_PLAYERS},
+    physics::{BallCollided, CollisionKind},
+    storage,
+};
+
+const STATS: &str = "stats";
+
+#[derive(Resource, Debug, Clone, Default, PartialEq, Serialize)]
+pub struct MatchStats {
+    /// Paddle hits in every finished rally, in the order they were played.
+    pub rallies: Vec<usize>,
+    /// Most paddle hits in a single rally.
+    pub longest_rally: usize,
+    /// Fastest the ball has left a paddle or gutter, in units per second.
+    pub max_ball_speed: f32,
+    /// Paddle hits by each player.
+    pub hits: [usize; MAX_PLAYERS],
+    /// Rallies served toward each player.
+    pub serves: [usize; MAX_PLAYERS],
+    /// Points each player won in the rallies served toward them.
+    pub points_on_serve: [usize; MAX_PLAYERS],
+    /// Points each player won per rally served toward them, or 0 before
+    /// their first serve.
+    pub points_per_serve: [f32; MAX_PLAYERS],
+    /// Paddle hits in the rally being played.
+    #[serde(skip)]
+    rally_hits: usize,
+    /// Points each player has scored in the rally being played, which can be
+    /// more than one with several balls in play.
+    #[serde(skip)]
+    rally_points: [usize; MAX_PLAYERS],
+}
+
+impl MatchStats {
+    pub fn hit(&mut self, by: Scorer) {
+        self.hits[by.index()] += 1;
+        self.rally_hits += 1;
+    }
+
+    pub fn point(&mut self, by: Scorer) {
+        self.rally_points[by.index()] += 1;
+    }
+
+    /// Closes the rally that was served toward `receiver`.
+    pub fn end_rally(&mut self, receiver: Scorer) {
+        let receiver = receiver.index();
+        self.serves[receiver] += 1;
+        self.points_on_serve[receiver] += self.rally_points[receiver];
+        self.points_per_serve[receiver] =
+            self.points_on_serve[receiver] as f32 / self.serves[receiver] as f32;
+
+        self.rallies.push(self.rally_hits);
+        self.longest_rally = self.longest_rally.max(self.rally_hits);
+        self.rally_hits = 0;
+        self.rally_points = [0; MAX_PLAYERS];
+    }
+
+    /// Mean paddle hits per finished rally.
+    pub fn average_rally(&self) -> f32 {
+        if self.rallies.is_empty() {
+            return 0.;
+        }
+        self.rallies.iter().sum::<usize>() as f32 / self.rallies.len() as f32
+    }
+
+    /// A few lines summing the match up for the players in `mode`, with the
+    /// player on the `player` edge.
+    pub fn summary(&self, mode: GameMode, player: Edge) -> String {
+        let per_player = |value: &dyn Fn(usize) -> String| {
+            mode.edges()
+                .iter()
+                .map(|&edge| {
+                    let label = label(edge, player);
+                    format!("{label} {}", value(edge.scorer().index()))
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        format!(
+            "Longest rally: {} hits ({:.1} on average)\n\
+             Fastest ball: {:.0}\n\
+             Hits: {}\n\
+             Points per serve: {}",
+            self.longest_rally,
+            self.average_rally(),
+            self.max_ball_speed,
+            per_player(&|player| self.hits[player].to_string()),
+            per_player(&|player| format!("{:.2}", self.points_per_serve[player])),
+        )
+    }
+}
+
+/// What the summary calls the paddle guarding `edge` when the player is on the
+/// `player` edge, the way the scoreboard's banner names them.
+fn label(edge: Edge, player: Edge) -> &'static str {
+    match edge {
+        _ if edge == player => "You",
+        Edge::Right | Edge::Left if edge == player.opposite() => "Opponent",
+        Edge::Right => "Right",
+        Edge::Left => "Left",
+        Edge::Top => "Top",
+        Edge::Bottom => "Bottom",
+    }
+}
+
+/// The player the ball is served toward, whose edge the serve heads for.
+fn receiver(serve: &Serve, mode: GameMode) -> Scorer {
+    let toward = |edge: &&Edge| edge.outward().dot(serve.direction);
+    mode.edges()
+        .iter()
+        .max_by(|a, b| toward(a).total_cmp(&toward(b)))
+        .map_or(Scorer::PLAYER, |edge| edge.scorer())
+}
+
+pub fn reset(mut stats: ResMut<MatchStats>) {
+    *stats = MatchStats::default();
+}
+
+pub fn count_hits(
+    mut stats: ResMut<MatchStats>,
+    mut collided: EventReader<BallCollided>,
+    paddles: Query<&Edge, With<Paddle>>,
+) {
+    for collided in collided.read() {
+        stats.max_ball_speed = stats.max_ball_speed.max(collided.speed);
+        if collided.kind != CollisionKind::Paddle {
+            continue;
+        }
+        if let Ok(edge) = paddles.get(collided.wall) {
+            stats.hit(edge.scorer());
+        }
+    }
+}
+
+/// Counts every point and closes the rally once the last ball is out. Runs
+/// before the next serve is picked, so it still knows who received this one.
+pub fn count_points(
+    mut stats: ResMut<MatchStats>,
+    mut scored: EventReader<PointScored>,
+    mut ended: EventReader<RallyEnded>,
+    serve: Res<Serve>,
+    mode: Res<GameMode>,
+) {
+    for scored in scored.read() {
+        stats.point(scored.by);
+    }
+    if !ended.is_empty() {
+        ended.clear();
+        stats.end_rally(receiver(&serve, *mode));
+    }
+}
+
+pub fn export(stats: Res<MatchStats>) {
+    storage::export(STATS, &*stats);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rallies_are_closed_with_their_hits_and_points() {
+        let mut stats = MatchStats::default();
+        for _ in 0..3 {
+            stats.hit(Scorer::PLAYER);
+            stats.hit(Scorer::OPPONENT);
+        }
+        stats.point(Scorer::PLAYER);
+        stats.end_rally(Scorer::PLAYER);
+
+        stats.hit(Scorer::OPPONENT);
+        stats.point(Scorer::OPPONENT);
+        stats.end_rally(Scorer::PLAYER);
+
+        assert_eq!(stats.rallies, [6, 1]);
+        assert_eq!(stats.longest_rally, 6);
+        assert_eq!(stats.average_rally(), 3.5);
+        assert_eq!(stats.hits, [3, 4, 0, 0]);
+        assert_eq!(stats.serves, [2, 0, 0, 0]);
+        assert_eq!(stats.points_per_serve, [0.5, 0., 0., 0.]);
+    }
+
+    #[test]
+    fn serves_are_received_by_the_edge_they_head_for() {
+        for edge in Edge::ALL {
+            let serve = Serve::toward(edge, GameMode::FourPlayer);
+            assert_eq!(receiver(&serve, GameMode::FourPlayer), edge.scorer());
+        }
+        let serve = Serve::toward(Edge::Left, GameMode::SinglePlayer);
+        assert_eq!(receiver(&serve, GameMode::SinglePlayer), Scorer::OPPONENT);
+    }
+
+    #[test]
+    fn summary_names_players_like_the_scoreboard() {
+        let mut stats = MatchStats::default();
+        stats.hit(Scorer::PLAYER);
+
+        let summary = stats.summary(GameMode::SinglePlayer, Edge::Right);
+        assert!(summary.contains("Hits: You 1, Opponent 0"), "{summary}");
+        assert!(stats
+            .summary(GameMode::FourPlayer, Edge::Right)
+            .contains("Top 0, Bottom 0"));
+    }
+
+    #[test]
+    fn summary_names_the_player_on_the_left_online() {
+        let mut stats = MatchStats::default();
+        stats.hit(Edge::Left.scorer());
+
+        let summary = stats.summary(GameMode::OnlineVersus, Edge::Left);
+        assert!(summary.contains("Hits: Opponent 0, You 1"), "{summary}");
+    }
+}
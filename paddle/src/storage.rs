@@ -0,0 +1,103 @@
+//! Persistence for small pieces of game data.
+//!
+//! Values are serialized to RON and stored under a name:
+//! - Natively as `<name>.ron` in a `paddle` folder of the platform config
+//!   directory (`~/.config/paddle` on Linux, for example)
+//! - In the browser as a `paddle.<name>` entry in `localStorage`
+//!
+//! Values can also be exported as JSON for other tools to read, natively as
+//! `<name>.json` in the same folder and in the browser under the same entry.
+//!
+//! Failing to read or write is never fatal; it is logged and the game carries
+//! on with whatever it has in memory.
+
+use bevy::log::warn;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Loads the value stored under `name`, if there is one and it can be read.
+pub fn load<T: DeserializeOwned>(name: &str) -> Option<T> {
+    let text = read(name)?;
+    match ron::from_str(&text) {
+        Ok(value) => Some(value),
+        Err(error) => {
+            warn!("Ignoring unreadable {name}: {error}");
+            None
+        }
+    }
+}
+
+/// Stores `value` under `name`, replacing whatever was there.
+pub fn save<T: Serialize>(name: &str, value: &T) {
+    match ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default()) {
+        Ok(text) => write(name, "ron", &text),
+        Err(error) => warn!("Could not serialize {name}: {error}"),
+    }
+}
+
+/// Stores `value` under `name` as JSON, replacing whatever was there.
+pub fn export<T: Serialize>(name: &str, value: &T) {
+    match serde_json::to_string_pretty(value) {
+        Ok(text) => write(name, "json", &text),
+        Err(error) => warn!("Could not serialize {name}: {error}"),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn path(name: &str, extension: &str) -> Option<std::path::PathBuf> {
+    Some(
+        dirs::config_dir()?
+            .join("paddle")
+            .join(format!("{name}.{extension}")),
+    )
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read(name: &str) -> Option<String> {
+    let path = path(name, "ron")?;
+    match std::fs::read_to_string(&path) {
+        Ok(text) => Some(text),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => None,
+        Err(error) => {
+            warn!("Could not read {}: {error}", path.display());
+            None
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write(name: &str, extension: &str, text: &str) {
+    let Some(path) = path(name, extension) else {
+        warn!("No config directory to save {name} to");
+        return;
+    };
+
+    let written = match path.parent() {
+        Some(dir) => std::fs::create_dir_all(dir).and_then(|_| std::fs::write(&path, text)),
+        None => std::fs::write(&path, text),
+    };
+    if let Err(error) = written {
+        warn!("Could not write {}: {error}", path.display());
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read(name: &str) -> Option<String> {
+    local_storage()?.get_item(&format!("paddle.{name}")).ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write(name: &str, _extension: &str, text: &str) {
+    let Some(storage) = local_storage() else {
+        warn!("No localStorage to save {name} to");
+        return;
+    };
+
+    if storage.set_item(&format!("paddle.{name}"), text).is_err() {
+        warn!("Could not save {name} to localStorage");
+    }
+}
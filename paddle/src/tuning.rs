@@ -0,0 +1,130 @@
+//! Tuning of the game's speeds, sizes and colors.
+//!
+//! Every value is held in the `PaddleTuning` resource, which the systems read
+//! each time they run. When drawn to a window, the game loads it from the
+//! `assets/tuning.ron` file, and natively it watches the file so any change is
+//! picked up while the game is running:
+//! - Speeds apply from the next tick, the ball's serve speed from the next serve
+//! - The ball, paddles and gutters are resized right away, and the ball and
+//!   paddles recolored when they are drawn in the classic palette
+//!
+//! Values left out of the file keep their defaults. A headless game does not
+//! load the file and plays with the defaults, so tests do not depend on it.
+
+use bevy::{
+    asset::Asset,
+    color::Color,
+    ecs::{
+        change_detection::DetectChangesMut,
+        resource::Resource,
+        system::{Res, ResMut},
+    },
+    log::info,
+    math::Vec2,
+    reflect::TypePath,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::Shape,
+    replay::Playback,
+    ron_asset::{Reloaded, RonAsset},
+};
+
+/// Where the tuning is loaded from, in the assets folder.
+pub const PATH: &str = "tuning.ron";
+
+#[derive(Asset, Resource, TypePath, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PaddleTuning {
+    /// Units per second.
+    pub paddle_speed: f32,
+    pub paddle_width: f32,
+    pub paddle_height: f32,
+    pub paddle_one_color: Color,
+    pub paddle_two_color: Color,
+
+    /// The ball's radius.
+    pub ball_size: f32,
+    pub ball_color: Color,
+    /// Units per second, when it is served.
+    pub ball_speed: f32,
+    /// Added to the ball's speed on every paddle hit in a rally.
+    pub ball_speed_increment: f32,
+    pub ball_max_speed: f32,
+
+    pub gutter_height: f32,
+}
+
+impl Default for PaddleTuning {
+    fn default() -> Self {
+        Self {
+            paddle_speed: 240.,
+            paddle_width: 10.,
+            paddle_height: 50.,
+            paddle_one_color: Color::srgb(1.0, 1.0, 1.0),
+            paddle_two_color: Color::srgb(0., 0., 1.),
+            ball_size: 5.,
+            ball_color: Color::srgb(1.0, 0.0, 0.0),
+            ball_speed: 420.,
+            ball_speed_increment: 30.,
+            ball_max_speed: 1000.,
+            gutter_height: 20.,
+        }
+    }
+}
+
+impl PaddleTuning {
+    /// Size of a paddle without any power-up.
+    pub fn paddle_size(&self) -> Vec2 {
+        Vec2::new(self.paddle_width, self.paddle_height)
+    }
+
+    pub fn ball_shape(&self) -> Shape {
+        Shape {
+            size: Vec2::splat(self.ball_size),
+        }
+    }
+}
+
+impl RonAsset for PaddleTuning {
+    const PATH: &'static str = PATH;
+}
+
+/// Copies the tuning into the `PaddleTuning` resource whenever the file is
+/// loaded or changes, unless a replay is played back with the tuning it was
+/// recorded with.
+pub fn apply(
+    mut file: Reloaded<PaddleTuning>,
+    mut tuning: ResMut<PaddleTuning>,
+    playback: Option<Res<Playback>>,
+) {
+    if let Some(loaded) = file.get() {
+        if playback.is_some() {
+            return;
+        }
+        if tuning.set_if_neq(loaded.clone()) {
+            info!("Applied the tuning from {PATH}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_values_keep_their_defaults() {
+        let tuning: PaddleTuning = ron::from_str("(paddle_speed: 300.0)").unwrap();
+
+        assert_eq!(tuning.paddle_speed, 300.);
+        assert_eq!(tuning.ball_speed, PaddleTuning::default().ball_speed);
+    }
+
+    #[test]
+    fn shipped_tuning_is_the_default() {
+        let tuning: PaddleTuning = ron::from_str(include_str!("../assets/tuning.ron")).unwrap();
+
+        assert_eq!(tuning, PaddleTuning::default());
+    }
+}
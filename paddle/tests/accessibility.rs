@@ -0,0 +1,52 @@
+//! Serves a headless match with the ball slowed down and checks it keeps the
+//! slower speed until the rally picks it up.
+
+mod common;
+
+use bevy::prelude::*;
+use paddle::{
+    arena::Arena,
+    components::{Ball, Speed, Velocity},
+    game::MatchRules,
+    tuning::PaddleTuning,
+    PaddlePlugin,
+};
+
+fn app(ball_speed: f32) -> App {
+    let mut app = common::app(PaddlePlugin::headless(Arena::default()));
+    app.insert_resource(MatchRules {
+        ball_speed,
+        ..Default::default()
+    });
+    app.update();
+    app
+}
+
+fn ball(app: &mut App) -> (Vec2, f32) {
+    app.world_mut()
+        .query_filtered::<(&Velocity, &Speed), With<Ball>>()
+        .single(app.world())
+        .map(|(velocity, speed)| (velocity.direction, speed.0))
+        .unwrap()
+}
+
+#[test]
+fn slowed_ball_is_served_slower() {
+    let mut app = app(0.5);
+    let tuned = app.world().resource::<PaddleTuning>().ball_speed;
+
+    assert_eq!(ball(&mut app).1, tuned * 0.5);
+    common::wait_for_rally(&mut app);
+    let (direction, speed) = ball(&mut app);
+    assert_ne!(direction, Vec2::ZERO);
+    assert_eq!(speed, tuned * 0.5);
+}
+
+#[test]
+fn full_speed_is_the_tuned_speed() {
+    let mut app = app(1.);
+    let tuned = app.world().resource::<PaddleTuning>().ball_speed;
+
+    common::wait_for_rally(&mut app);
+    assert_eq!(ball(&mut app).1, tuned);
+}
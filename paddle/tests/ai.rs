@@ -0,0 +1,93 @@
+//! Plays headless matches between AI paddles to check that the difficulty
+//! presets are ordered: a harder AI should win most points against an easier one.
+
+mod common;
+
+use bevy::prelude::*;
+use leafwing_input_manager::InputManagerBundle;
+use paddle::{
+    ai::{Difficulty, OpponentAi},
+    arena::Arena,
+    components::Player,
+    game::{AppState, GameRng, MatchRules, Score, Scorer},
+    input::Action,
+    PaddlePlugin,
+};
+
+/// Upper bound on simulated time per match, so a stalemate fails rather than hangs.
+const MAX_TICKS: usize = 64 * 60 * 10;
+
+/// Plays one match to `points` with `left` as the opponent's AI and `right`
+/// taking over the player's paddle. Returns the points won by each side.
+fn play(left: Difficulty, right: Difficulty, seed: u64, points: usize) -> (usize, usize) {
+    let mut app = common::app(PaddlePlugin {
+        difficulty: left,
+        ..PaddlePlugin::headless(Arena::default())
+    });
+    app.insert_resource(GameRng::from_seed(seed))
+        .insert_resource(MatchRules {
+            points_to_win: points,
+            win_by_two: false,
+            best_of: 1,
+            ..Default::default()
+        });
+
+    app.update();
+
+    let player = app
+        .world_mut()
+        .query_filtered::<Entity, With<Player>>()
+        .single(app.world())
+        .unwrap();
+    app.world_mut()
+        .entity_mut(player)
+        .remove::<InputManagerBundle<Action>>()
+        .insert(OpponentAi::new(right));
+
+    for _ in 0..MAX_TICKS {
+        app.update();
+        if *app.world().resource::<State<AppState>>().get() == AppState::GameOver {
+            break;
+        }
+    }
+
+    let score = app.world().resource::<Score>();
+    (score.points(Scorer::OPPONENT), score.points(Scorer::PLAYER))
+}
+
+/// Share of the points won by `stronger` over a few seeded matches.
+fn win_rate(stronger: Difficulty, weaker: Difficulty) -> f32 {
+    let (mut won, mut lost) = (0, 0);
+    for seed in 0..4 {
+        // Swap sides every match so neither side's serve decides the result
+        let (stronger_points, weaker_points) = if seed % 2 == 0 {
+            play(stronger, weaker, seed, 7)
+        } else {
+            let (weaker_points, stronger_points) = play(weaker, stronger, seed, 7);
+            (stronger_points, weaker_points)
+        };
+        won += stronger_points;
+        lost += weaker_points;
+    }
+
+    assert!(won + lost > 0, "no points were played");
+    won as f32 / (won + lost) as f32
+}
+
+#[test]
+fn hard_beats_easy() {
+    let rate = win_rate(Difficulty::Hard, Difficulty::Easy);
+    assert!(rate >= 0.75, "hard won {rate:.2} of points against easy");
+}
+
+#[test]
+fn hard_beats_medium() {
+    let rate = win_rate(Difficulty::Hard, Difficulty::Medium);
+    assert!(rate > 0.5, "hard won {rate:.2} of points against medium");
+}
+
+#[test]
+fn medium_beats_easy() {
+    let rate = win_rate(Difficulty::Medium, Difficulty::Easy);
+    assert!(rate > 0.5, "medium won {rate:.2} of points against easy");
+}
@@ -0,0 +1,138 @@
+//! Plays headless breakout games and checks that the paddle guards the bottom
+//! between three walls, that bricks break for points and clear the level, and
+//! that losing every ball is game over.
+
+mod common;
+
+use bevy::prelude::*;
+use paddle::{
+    arena::Arena,
+    breakout::{Brick, CurrentLevel, Levels, Lives},
+    components::{Ball, Edge, Gutter, Paddle, Player, Position, Velocity},
+    game::{AppState, GameMode, Score},
+    tuning::PaddleTuning,
+    PaddlePlugin,
+};
+
+fn app() -> App {
+    let mut app = common::app(PaddlePlugin {
+        mode: GameMode::Breakout,
+        ..PaddlePlugin::headless(Arena::default())
+    });
+    app.update();
+    app
+}
+
+fn bricks(app: &mut App) -> Vec<(Entity, Vec2)> {
+    app.world_mut()
+        .query_filtered::<(Entity, &Position), With<Brick>>()
+        .iter(app.world())
+        .map(|(entity, position)| (entity, position.coords))
+        .collect()
+}
+
+fn level_size(app: &App, level: usize) -> usize {
+    app.world().resource::<Levels>().0[level]
+        .bricks(
+            app.world().resource::<Arena>(),
+            app.world().resource::<PaddleTuning>(),
+        )
+        .unwrap()
+        .len()
+}
+
+fn send_ball(app: &mut App, from: Vec2, direction: Vec2) {
+    let ball = app
+        .world_mut()
+        .query_filtered::<Entity, With<Ball>>()
+        .single(app.world())
+        .unwrap();
+    app.world_mut()
+        .entity_mut(ball)
+        .insert((Position { coords: from }, Velocity { direction }));
+}
+
+#[test]
+fn paddle_guards_the_bottom_between_three_walls() {
+    let mut app = app();
+
+    let paddles: Vec<Edge> = app
+        .world_mut()
+        .query_filtered::<&Edge, With<Paddle>>()
+        .iter(app.world())
+        .copied()
+        .collect();
+    assert_eq!(paddles, [Edge::Bottom]);
+    let player = app
+        .world_mut()
+        .query_filtered::<&Edge, With<Player>>()
+        .single(app.world())
+        .copied()
+        .unwrap();
+    assert_eq!(player, Edge::Bottom);
+
+    let mut walls: Vec<Edge> = app
+        .world_mut()
+        .query::<&Gutter>()
+        .iter(app.world())
+        .map(|gutter| gutter.0)
+        .collect();
+    walls.sort_by_key(|edge| *edge as usize);
+    assert_eq!(walls, [Edge::Right, Edge::Left, Edge::Top]);
+
+    // The bricks are laid in the first tick
+    app.update();
+    assert_eq!(bricks(&mut app).len(), level_size(&app, 0));
+}
+
+#[test]
+fn breaking_the_last_brick_clears_the_level() {
+    let mut app = app();
+    common::wait_for_rally(&mut app);
+
+    // Leave one brick standing, with a single hit point, right above the ball
+    let mut bricks = bricks(&mut app);
+    let (last, above) = bricks.pop().unwrap();
+    for (entity, _) in bricks {
+        app.world_mut().entity_mut(entity).despawn();
+    }
+    app.world_mut()
+        .entity_mut(last)
+        .insert(Brick { hit_points: 1 });
+    send_ball(&mut app, above - Vec2::new(0., 40.), Vec2::Y);
+
+    for _ in 0..64 {
+        app.update();
+        if app.world().resource::<CurrentLevel>().0 == 1 {
+            break;
+        }
+    }
+    assert_eq!(*app.world().resource::<CurrentLevel>(), CurrentLevel(1));
+    assert_eq!(app.world().resource::<Score>().points, [0, 0, 0, 1]);
+    assert_eq!(*app.world().resource::<Lives>(), Lives(3));
+
+    app.update();
+    assert_eq!(self::bricks(&mut app).len(), level_size(&app, 1));
+}
+
+#[test]
+fn losing_the_last_ball_is_game_over() {
+    let mut app = app();
+    common::wait_for_rally(&mut app);
+
+    let half_height = app.world().resource::<Arena>().half_height();
+    for lives in (0..3).rev() {
+        common::wait_for_rally(&mut app);
+        send_ball(&mut app, Vec2::new(0., 1. - half_height), Vec2::NEG_Y);
+        app.update();
+        assert_eq!(*app.world().resource::<Lives>(), Lives(lives));
+    }
+
+    app.update();
+    assert_eq!(
+        *app.world().resource::<State<AppState>>().get(),
+        AppState::GameOver
+    );
+    // Losing a ball is not a point for anyone
+    assert_eq!(app.world().resource::<Score>().points, [0; 4]);
+}
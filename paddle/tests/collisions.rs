@@ -0,0 +1,56 @@
+//! Watches the `BallCollided` events of a headless match, which is what the
+//! sound effects play from.
+
+mod common;
+
+use bevy::{ecs::event::EventCursor, prelude::*};
+use paddle::{
+    arena::Arena,
+    components::Speed,
+    physics::{BallCollided, Collision, CollisionKind},
+    PaddlePlugin,
+};
+
+/// Upper bound on updates, so a ball that never bounces fails rather than hangs.
+const MAX_UPDATES: usize = 64 * 60;
+
+/// Steps a headless match and collects every collision until `until` is met.
+fn collisions_until(until: impl Fn(&[BallCollided]) -> bool) -> Vec<BallCollided> {
+    let mut app = common::app(PaddlePlugin::headless(Arena::default()));
+
+    let mut cursor = EventCursor::<BallCollided>::default();
+    let mut collisions = Vec::new();
+    for _ in 0..MAX_UPDATES {
+        app.update();
+        let events = app.world().resource::<Events<BallCollided>>();
+        collisions.extend(cursor.read(events).copied());
+        if until(&collisions) {
+            return collisions;
+        }
+    }
+    panic!("still waiting after {MAX_UPDATES} updates: {collisions:?}");
+}
+
+#[test]
+fn served_ball_bounces_off_the_top_gutter_first() {
+    let collisions = collisions_until(|collisions| !collisions.is_empty());
+
+    let first = collisions[0];
+    assert_eq!(first.kind, CollisionKind::Gutter);
+    // The ball is served upwards, so it touches the underside of the gutter
+    assert_eq!(first.with, Collision::Bottom);
+    assert_eq!(first.speed, Speed::default().0);
+}
+
+#[test]
+fn paddle_hits_report_the_ball_speeding_up() {
+    let collisions = collisions_until(|collisions| {
+        collisions
+            .iter()
+            .any(|collision| collision.kind == CollisionKind::Paddle)
+    });
+
+    let hit = collisions.last().unwrap();
+    assert!(matches!(hit.with, Collision::Left | Collision::Right));
+    assert!(hit.speed > Speed::default().0);
+}
@@ -0,0 +1,42 @@
+//! Setup shared by the integration tests.
+
+// Every test file includes this module but none of them use all of it
+#![allow(dead_code)]
+
+use std::time::Duration;
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use paddle::{game::MatchState, PaddlePlugin};
+
+/// How long a fixed tick of the game lasts.
+pub const TICK: f64 = 1. / 64.;
+
+/// A headless app running `plugin` one fixed tick per update. Nothing has run
+/// yet, so resources inserted before the first update are in place when the
+/// match starts.
+pub fn app(plugin: PaddlePlugin) -> App {
+    app_updating_every(plugin, TICK)
+}
+
+/// Like `app`, with `seconds` of time passing on every update, so updates can
+/// run more or fewer fixed ticks than one.
+pub fn app_updating_every(plugin: PaddlePlugin, seconds: f64) -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(plugin)
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+            seconds,
+        )));
+    app
+}
+
+/// Runs until the ball has been served.
+pub fn wait_for_rally(app: &mut App) {
+    for _ in 0..64 * 5 {
+        app.update();
+        if *app.world().resource::<State<MatchState>>().get() == MatchState::Rally {
+            return;
+        }
+    }
+    panic!("the ball was never served");
+}
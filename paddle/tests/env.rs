@@ -0,0 +1,135 @@
+//! Steps the gym-style environment through whole matches, checking that it is
+//! rewarded for the points scored and plays out the same for the same seed and
+//! actions.
+
+use paddle::{
+    arena::Arena,
+    breakout::{self, Lives, LIVES},
+    env::{EnvError, Move, Observation, PaddleEnv},
+    game::{GameMode, MatchRules, Score, Scorer},
+};
+
+/// Upper bound on steps per match, so a stalemate fails rather than hangs.
+const MAX_STEPS: usize = 64 * 60 * 10;
+
+fn env() -> PaddleEnv {
+    let mut env = PaddleEnv::new(Arena::default(), GameMode::SinglePlayer);
+    env.rules = MatchRules {
+        points_to_win: 3,
+        win_by_two: false,
+        best_of: 1,
+        ..Default::default()
+    };
+    env
+}
+
+/// Follows the first ball along the paddle's lane, which is good enough to
+/// return most serves.
+fn follow(observation: &Observation) -> Move {
+    let offset = observation.balls[0].position.y - observation.player.position.y;
+    if offset > 10. {
+        Move::Up
+    } else if offset < -10. {
+        Move::Down
+    } else {
+        Move::Stay
+    }
+}
+
+/// Plays a match out, returning every observation and the total reward.
+fn play(env: &mut PaddleEnv, seed: u64) -> (Vec<Observation>, f32) {
+    let mut observation = env.reset(seed).unwrap();
+    let mut observations = vec![observation.clone()];
+    let mut total = 0.;
+    for _ in 0..MAX_STEPS {
+        let (next, reward, done) = env.step(follow(&observation));
+        total += reward;
+        observation = next;
+        observations.push(observation.clone());
+        if done {
+            return (observations, total);
+        }
+    }
+    panic!("the match never finished");
+}
+
+#[test]
+fn rewards_add_up_to_the_score() {
+    let mut env = env();
+    let (observations, total) = play(&mut env, 7);
+
+    let score = env.world().resource::<Score>();
+    let difference = score.points(Scorer::PLAYER) as f32 - score.points(Scorer::OPPONENT) as f32;
+    assert_eq!(total, difference);
+
+    // The player's paddle moved where it was told to
+    assert!(observations
+        .iter()
+        .any(|observation| observation.player.velocity.y != 0.));
+    assert_eq!(observations[0].opponents.len(), 1);
+}
+
+#[test]
+fn same_seed_plays_the_same_match() {
+    let mut env = env();
+    let first = play(&mut env, 3);
+    let second = play(&mut env, 3);
+
+    assert_eq!(first, second);
+}
+
+#[test]
+fn standing_still_loses_points() {
+    let mut env = env();
+    env.reset(1).unwrap();
+
+    let mut total = 0.;
+    for _ in 0..MAX_STEPS {
+        let (_, reward, done) = env.step(Move::Stay);
+        total += reward;
+        if done {
+            break;
+        }
+    }
+    assert!(total < 0., "got {total}");
+}
+
+#[test]
+fn online_matches_are_refused() {
+    let mut env = env();
+    env.mode = GameMode::OnlineVersus;
+
+    assert_eq!(env.reset(1), Err(EnvError::Online));
+}
+
+#[test]
+fn breaking_bricks_is_rewarded() {
+    let mut env = PaddleEnv::new(Arena::default(), GameMode::Breakout);
+    let mut observation = env.reset(5).unwrap();
+
+    let mut total = 0.;
+    let mut rewarded = false;
+    for _ in 0..MAX_STEPS {
+        // Up and down run along the bottom edge
+        let offset = observation.balls[0].position.x - observation.player.position.x;
+        let action = if offset > 10. {
+            Move::Up
+        } else if offset < -10. {
+            Move::Down
+        } else {
+            Move::Stay
+        };
+        let (next, reward, done) = env.step(action);
+        total += reward;
+        rewarded |= reward > 0.;
+        observation = next;
+        if done {
+            break;
+        }
+    }
+
+    let broken = env.world().resource::<Score>().points(breakout::player());
+    let lost = LIVES - env.world().resource::<Lives>().0;
+    assert!(rewarded, "no brick was ever broken");
+    assert_eq!(total, broken as f32 - lost as f32);
+}
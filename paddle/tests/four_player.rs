@@ -0,0 +1,101 @@
+//! Plays headless four player matches and checks that paddles guard every
+//! edge, that goals score for whoever last touched the ball, and that the AI
+//! paddles play a match out.
+
+mod common;
+
+use bevy::prelude::*;
+use paddle::{
+    arena::Arena,
+    components::{Ball, Edge, Gutter, LastTouched, Paddle, Position, Shape, Velocity},
+    game::{AppState, GameMode, MatchRules, Score},
+    PaddlePlugin,
+};
+
+fn app() -> App {
+    let mut app = common::app(PaddlePlugin {
+        mode: GameMode::FourPlayer,
+        ..PaddlePlugin::headless(Arena::default())
+    });
+    app.update();
+    app
+}
+
+fn paddle_on(app: &mut App, edge: Edge) -> Entity {
+    app.world_mut()
+        .query_filtered::<(Entity, &Edge), With<Paddle>>()
+        .iter(app.world())
+        .find(|(_, paddle)| **paddle == edge)
+        .map(|(entity, _)| entity)
+        .unwrap()
+}
+
+#[test]
+fn paddles_guard_every_edge_instead_of_gutters() {
+    let mut app = app();
+
+    let mut edges: Vec<(Edge, Vec2)> = app
+        .world_mut()
+        .query_filtered::<(&Edge, &Shape), With<Paddle>>()
+        .iter(app.world())
+        .map(|(edge, shape)| (*edge, shape.size))
+        .collect();
+    edges.sort_by_key(|(edge, _)| *edge as usize);
+
+    assert_eq!(
+        edges.iter().map(|(edge, _)| *edge).collect::<Vec<_>>(),
+        Edge::ALL
+    );
+    // The top and bottom paddles lie along their edge
+    for (edge, size) in edges {
+        assert_eq!(size.y > size.x, edge.axis() == Vec2::Y);
+    }
+
+    let gutters = app
+        .world_mut()
+        .query_filtered::<(), With<Gutter>>()
+        .iter(app.world())
+        .count();
+    assert_eq!(gutters, 0);
+}
+
+#[test]
+fn goal_scores_for_whoever_last_touched_the_ball() {
+    let mut app = app();
+    common::wait_for_rally(&mut app);
+
+    let left = paddle_on(&mut app, Edge::Left);
+    let half_height = app.world().resource::<Arena>().half_height();
+    let ball = app
+        .world_mut()
+        .query_filtered::<Entity, With<Ball>>()
+        .single(app.world())
+        .unwrap();
+    app.world_mut().entity_mut(ball).insert((
+        Position {
+            coords: Vec2::new(0., half_height - 1.),
+        },
+        Velocity { direction: Vec2::Y },
+        LastTouched(Some(left)),
+    ));
+    app.update();
+
+    assert_eq!(app.world().resource::<Score>().points, [0, 1, 0, 0]);
+}
+
+#[test]
+fn ai_paddles_play_a_match_out() {
+    let mut app = app();
+    app.insert_resource(MatchRules {
+        points_to_win: 3,
+        ..Default::default()
+    });
+
+    for _ in 0..64 * 600 {
+        app.update();
+        if *app.world().resource::<State<AppState>>().get() == AppState::GameOver {
+            return;
+        }
+    }
+    panic!("the match never ended");
+}
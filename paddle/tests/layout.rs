@@ -0,0 +1,141 @@
+//! Plays headless matches in arenas with obstacles, and checks that the ball
+//! bounces off them and that they move as their layout says.
+
+mod common;
+
+use bevy::{ecs::event::EventCursor, prelude::*};
+use paddle::{
+    arena::Arena,
+    components::{Position, Shape},
+    game::GameMode,
+    layout::{Gap, Layout, Obstacle, ObstacleKind},
+    physics::{BallCollided, CollisionKind},
+    PaddlePlugin,
+};
+
+fn app(mode: GameMode, obstacles: Vec<ObstacleKind>) -> App {
+    let mut app = common::app(PaddlePlugin {
+        mode,
+        ..PaddlePlugin::headless(Arena::default())
+    });
+    app.insert_resource(Layout {
+        name: "Test".into(),
+        obstacles,
+    });
+    app.update();
+    app
+}
+
+fn obstacles(app: &mut App) -> Vec<(Vec2, Vec2)> {
+    let mut obstacles: Vec<_> = app
+        .world_mut()
+        .query_filtered::<(&Position, &Shape), With<Obstacle>>()
+        .iter(app.world())
+        .map(|(position, shape)| (position.coords, shape.size))
+        .collect();
+    obstacles.sort_by(|a, b| a.0.y.total_cmp(&b.0.y));
+    obstacles
+}
+
+#[test]
+fn served_ball_bounces_off_a_bumper_in_its_way() {
+    // The ball is served up and to the right from the center
+    let mut app = app(
+        GameMode::SinglePlayer,
+        vec![ObstacleKind::Bumper {
+            center: Vec2::new(150., 150.),
+            size: Vec2::splat(40.),
+        }],
+    );
+
+    let mut cursor = EventCursor::<BallCollided>::default();
+    for _ in 0..64 * 5 {
+        app.update();
+        let events = app.world().resource::<Events<BallCollided>>();
+        if let Some(first) = cursor.read(events).next() {
+            assert_eq!(first.kind, CollisionKind::Obstacle);
+            return;
+        }
+    }
+    panic!("the ball never bounced");
+}
+
+#[test]
+fn walls_are_split_around_their_gaps() {
+    let mut app = app(
+        GameMode::SinglePlayer,
+        vec![ObstacleKind::Wall {
+            x: 0.,
+            thickness: 10.,
+            gaps: vec![Gap {
+                center: 0.,
+                size: 100.,
+            }],
+        }],
+    );
+
+    let half_height = app.world().resource::<Arena>().half_height();
+    let length = half_height - 50.;
+    assert_eq!(
+        obstacles(&mut app),
+        [
+            (Vec2::new(0., -50. - length / 2.), Vec2::new(10., length)),
+            (Vec2::new(0., 50. + length / 2.), Vec2::new(10., length)),
+        ]
+    );
+}
+
+#[test]
+fn obstacles_slide_and_turn() {
+    let mut app = app(
+        GameMode::SinglePlayer,
+        vec![
+            ObstacleKind::Sliding {
+                from: Vec2::new(-300., -200.),
+                to: Vec2::new(-300., 200.),
+                size: Vec2::new(20., 80.),
+                period: 2.,
+            },
+            ObstacleKind::Rotating {
+                center: Vec2::new(300., 250.),
+                length: 100.,
+                thickness: 10.,
+                interval: 1.,
+            },
+        ],
+    );
+
+    // Half a period in, the slider is at the other end and the blocker has
+    // not turned yet
+    for _ in 0..63 {
+        app.update();
+    }
+    let [slider, blocker] = obstacles(&mut app)[..] else {
+        panic!("expected two obstacles");
+    };
+    assert!((slider.0 - Vec2::new(-300., 200.)).length() < 1.);
+    assert_eq!(blocker.1, Vec2::new(100., 10.));
+
+    app.update();
+    let [_, blocker] = obstacles(&mut app)[..] else {
+        panic!("expected two obstacles");
+    };
+    assert_eq!(blocker.1, Vec2::new(10., 100.));
+}
+
+#[test]
+fn four_player_matches_skip_layouts_in_the_way_of_their_paddles() {
+    let mut app = app(
+        GameMode::FourPlayer,
+        vec![ObstacleKind::Wall {
+            x: 0.,
+            thickness: 10.,
+            gaps: vec![Gap {
+                center: 0.,
+                size: 100.,
+            }],
+        }],
+    );
+
+    assert!(obstacles(&mut app).is_empty());
+}
@@ -0,0 +1,97 @@
+//! Sends extra balls out of a headless match and checks that each one scores,
+//! while the rally only ends with the last ball.
+
+mod common;
+
+use bevy::prelude::*;
+use paddle::{
+    arena::Arena,
+    components::{Ball, Position, Velocity},
+    game::{MatchState, Score},
+    PaddlePlugin,
+};
+
+fn app() -> App {
+    let mut app = common::app(PaddlePlugin::headless(Arena::default()));
+    app.update();
+    app
+}
+
+/// Adds a ball just about to leave the arena on the side of `x`.
+fn spawn_leaving(app: &mut App, x: f32) -> Entity {
+    app.world_mut()
+        .spawn((
+            Ball,
+            Position {
+                coords: Vec2::new(x, 0.),
+            },
+            Velocity {
+                direction: Vec2::new(x.signum(), 0.),
+            },
+        ))
+        .id()
+}
+
+fn balls(app: &mut App) -> Vec<Entity> {
+    app.world_mut()
+        .query_filtered::<Entity, With<Ball>>()
+        .iter(app.world())
+        .collect()
+}
+
+#[test]
+fn extra_balls_score_without_ending_the_rally() {
+    let mut app = app();
+    common::wait_for_rally(&mut app);
+
+    let half_width = app.world().resource::<Arena>().half_width();
+    let left = spawn_leaving(&mut app, -half_width + 1.);
+    let right = spawn_leaving(&mut app, half_width - 1.);
+
+    for _ in 0..5 {
+        app.update();
+    }
+
+    let score = app.world().resource::<Score>();
+    assert_eq!(score.points, [1, 1, 0, 0]);
+    let remaining = balls(&mut app);
+    assert_eq!(remaining.len(), 1);
+    assert!(!remaining.contains(&left) && !remaining.contains(&right));
+    assert_eq!(
+        *app.world().resource::<State<MatchState>>().get(),
+        MatchState::Rally
+    );
+}
+
+#[test]
+fn last_ball_out_ends_the_rally() {
+    let mut app = app();
+    common::wait_for_rally(&mut app);
+
+    let half_width = app.world().resource::<Arena>().half_width();
+    let extra = spawn_leaving(&mut app, -half_width + 1.);
+    for _ in 0..5 {
+        app.update();
+    }
+    assert_eq!(balls(&mut app).len(), 1);
+
+    // Send the served ball out too
+    let last = balls(&mut app)[0];
+    assert_ne!(last, extra);
+    app.world_mut().get_mut::<Position>(last).unwrap().coords = Vec2::new(half_width + 1., 0.);
+    // The pause after the point starts on the next tick
+    app.update();
+    app.update();
+
+    let score = app.world().resource::<Score>();
+    assert_eq!(score.points.iter().sum::<usize>(), 2);
+    assert_eq!(balls(&mut app), vec![last]);
+    assert_ne!(
+        *app.world().resource::<State<MatchState>>().get(),
+        MatchState::Rally
+    );
+    assert_eq!(
+        app.world().get::<Position>(last).unwrap().coords,
+        Vec2::ZERO
+    );
+}
@@ -0,0 +1,125 @@
+//! Plays an online match headlessly in a GGRS sync test session, which rolls
+//! every frame back and steps it again, checking that the simulation comes out
+//! the same each time.
+
+mod common;
+
+use bevy::prelude::*;
+use bevy_ggrs::{
+    ggrs::{PlayerType, SessionBuilder},
+    Session,
+};
+use leafwing_input_manager::prelude::ActionState;
+use paddle::{
+    arena::Arena,
+    components::{Paddle, Player, Position},
+    game::{AppState, GameMode, MatchRules, PointScored, Score},
+    input::{self, Action, MOVE_DOWN, MOVE_UP},
+    session::{Config, OnlinePlayer},
+    PaddlePlugin,
+};
+
+/// Upper bound on updates per match, so a stalemate fails rather than hangs.
+const MAX_UPDATES: usize = 60 * 60 * 10;
+
+/// Number of points the scoreboard, sounds and effects were told about.
+#[derive(Resource, Default)]
+struct PointsShown(usize);
+
+fn show_points(mut scored: EventReader<PointScored>, mut shown: ResMut<PointsShown>) {
+    shown.0 += scored.read().count();
+}
+
+fn sync_test_app() -> App {
+    let session = SessionBuilder::<Config>::new()
+        .with_num_players(2)
+        .with_check_distance(2)
+        .add_player(PlayerType::Local, 0)
+        .and_then(|builder| builder.add_player(PlayerType::Local, 1))
+        .and_then(SessionBuilder::start_synctest_session)
+        .expect("sync test session");
+
+    // GGRS steps frames at 60 per second rather than the fixed 64
+    let mut app = common::app_updating_every(
+        PaddlePlugin {
+            mode: GameMode::OnlineVersus,
+            ..PaddlePlugin::headless(Arena::default())
+        },
+        1. / 60.,
+    );
+    app.insert_resource(MatchRules {
+        points_to_win: 3,
+        win_by_two: false,
+        best_of: 1,
+        ..Default::default()
+    })
+    .insert_resource(Session::SyncTest(session))
+    .init_resource::<PointsShown>()
+    .add_systems(Update, show_points);
+    app.update();
+    app
+}
+
+/// Holds movement on the local paddle, which a sync test sends as the input
+/// of both players.
+fn hold(app: &mut App, held: u8) {
+    let mut action = app
+        .world_mut()
+        .query_filtered::<&mut ActionState<Action>, With<Player>>()
+        .single_mut(app.world_mut())
+        .unwrap();
+    input::hold(&mut action, held);
+}
+
+#[test]
+fn every_paddle_follows_its_players_input() {
+    let mut app = sync_test_app();
+
+    hold(&mut app, MOVE_UP);
+    for _ in 0..30 {
+        app.update();
+    }
+
+    let mut paddles = app
+        .world_mut()
+        .query_filtered::<(&Position, &OnlinePlayer), With<Paddle>>();
+    let mut players: Vec<usize> = paddles
+        .iter(app.world())
+        .map(|(position, player)| {
+            assert!(position.coords.y > 0., "paddle did not move up");
+            player.0
+        })
+        .collect();
+    players.sort();
+    assert_eq!(players, vec![0, 1]);
+}
+
+#[test]
+fn online_match_is_played_to_the_end() {
+    let mut app = sync_test_app();
+
+    for update in 0..MAX_UPDATES {
+        let held = if update / 45 % 2 == 0 {
+            MOVE_UP
+        } else {
+            MOVE_DOWN
+        };
+        hold(&mut app, held);
+        app.update();
+
+        if *app.world().resource::<State<AppState>>().get() == AppState::GameOver {
+            let score = app.world().resource::<Score>();
+            assert_eq!(score.points.iter().max(), Some(&3));
+            // Points are shown once, however often their frame is stepped
+            assert_eq!(
+                app.world().resource::<PointsShown>().0,
+                score.points.iter().sum::<usize>()
+            );
+            // The session is dropped once the match is over
+            assert!(!app.world().contains_resource::<Session<Config>>());
+            return;
+        }
+    }
+
+    panic!("the match did not finish within {MAX_UPDATES} updates");
+}
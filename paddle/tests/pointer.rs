@@ -0,0 +1,54 @@
+//! Points the player's paddle somewhere in a headless match, as a touch or the
+//! mouse would, and checks that it gets there no faster than the keyboard.
+
+mod common;
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use paddle::{
+    arena::Arena,
+    components::{Player, Position},
+    input::{self, PointerTarget},
+    tuning::PaddleTuning,
+    PaddlePlugin,
+};
+
+fn player_y(app: &mut App) -> f32 {
+    app.world_mut()
+        .query_filtered::<&Position, With<Player>>()
+        .single(app.world())
+        .unwrap()
+        .coords
+        .y
+}
+
+#[test]
+fn paddle_follows_the_pointer_at_paddle_speed() {
+    let mut app = common::app(PaddlePlugin::headless(Arena::default()));
+    app.update();
+
+    let player = app
+        .world_mut()
+        .query_filtered::<Entity, With<Player>>()
+        .single(app.world())
+        .unwrap();
+    app.world_mut()
+        .entity_mut(player)
+        .insert(PointerTarget(Some(100.)));
+
+    let step = PaddleTuning::default().paddle_speed / 64.;
+    let mut last = player_y(&mut app);
+    for _ in 0..64 {
+        app.update();
+        let y = player_y(&mut app);
+        assert!(y - last <= step + 1e-3, "moved faster than the keyboard");
+        last = y;
+    }
+
+    let dead_zone = input::pointer_dead_zone(
+        PaddleTuning::default().paddle_speed,
+        Duration::from_secs_f64(1. / 64.),
+    );
+    assert!((last - 100.).abs() <= dead_zone);
+}
@@ -0,0 +1,112 @@
+//! Places power-ups in the ball's path in a headless match and checks what
+//! collecting them does.
+
+mod common;
+
+use bevy::prelude::*;
+use paddle::{
+    arena::Arena,
+    components::{Ball, LastTouched, Player, Position, Shape},
+    game::MatchRules,
+    powerup::{Modifier, PowerUp, PowerUpKind, PowerUpSpawner},
+    tuning::PaddleTuning,
+    PaddlePlugin,
+};
+
+fn app() -> App {
+    let mut app = common::app(PaddlePlugin::headless(Arena::default()));
+    app.insert_resource(MatchRules {
+        power_ups: true,
+        ..Default::default()
+    })
+    // Only the power-ups placed by the tests
+    .insert_resource(PowerUpSpawner(Timer::from_seconds(
+        1000.,
+        TimerMode::Repeating,
+    )));
+    app.update();
+    app
+}
+
+/// Waits for the serve, then places a power-up right in front of the ball.
+fn place_in_path(app: &mut App, kind: PowerUpKind) {
+    for _ in 0..80 {
+        app.update();
+    }
+
+    let ball = app
+        .world_mut()
+        .query_filtered::<&Position, With<Ball>>()
+        .single(app.world())
+        .unwrap()
+        .coords;
+    let serve = Vec2::new(1., 1.).normalize();
+    app.world_mut().spawn((
+        PowerUp(kind),
+        Position {
+            coords: ball + serve * 30.,
+        },
+    ));
+}
+
+fn ball_count(app: &mut App) -> usize {
+    app.world_mut()
+        .query_filtered::<(), With<Ball>>()
+        .iter(app.world())
+        .count()
+}
+
+#[test]
+fn multi_ball_splits_the_ball_in_three() {
+    let mut app = app();
+    place_in_path(&mut app, PowerUpKind::MultiBall);
+
+    for _ in 0..10 {
+        app.update();
+    }
+
+    assert_eq!(ball_count(&mut app), 3);
+    assert_eq!(
+        app.world_mut()
+            .query::<&PowerUp>()
+            .iter(app.world())
+            .count(),
+        0
+    );
+}
+
+#[test]
+fn enlarge_lasts_until_it_expires() {
+    let mut app = app();
+    place_in_path(&mut app, PowerUpKind::Enlarge);
+
+    let player = app
+        .world_mut()
+        .query_filtered::<Entity, With<Player>>()
+        .single(app.world())
+        .unwrap();
+    *app.world_mut()
+        .query_filtered::<&mut LastTouched, With<Ball>>()
+        .single_mut(app.world_mut())
+        .unwrap() = LastTouched(Some(player));
+
+    for _ in 0..10 {
+        app.update();
+    }
+
+    let paddle_size = PaddleTuning::default().paddle_size();
+    let shape = *app.world().get::<Shape>(player).unwrap();
+    assert!(shape.size.y > paddle_size.y);
+    assert_eq!(
+        app.world().get::<Modifier>(player).unwrap().kind,
+        PowerUpKind::Enlarge
+    );
+
+    // Long enough for the power-up to run out, whatever happens to the ball
+    for _ in 0..64 * 10 {
+        app.update();
+    }
+
+    assert_eq!(app.world().get::<Shape>(player).unwrap().size, paddle_size);
+    assert!(app.world().get::<Modifier>(player).is_none());
+}
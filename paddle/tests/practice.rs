@@ -0,0 +1,136 @@
+//! Plays headless practice sessions and checks that the launcher serves every
+//! shot of the drill as scripted, and that returns and misses are counted.
+
+mod common;
+
+use bevy::prelude::*;
+use paddle::{
+    arena::Arena,
+    components::{Ball, Edge, Gutter, Paddle, Position, Speed, Velocity},
+    game::{AppState, GameMode, MatchState, Score},
+    practice::{Drill, DrillProgress, Shot},
+    PaddlePlugin,
+};
+
+fn app(wall: bool, shots: Vec<Shot>) -> App {
+    let mut app = common::app(PaddlePlugin {
+        mode: GameMode::Practice,
+        ..PaddlePlugin::headless(Arena::default())
+    });
+    app.insert_resource(Drill {
+        name: "Test".into(),
+        wall,
+        shots,
+    });
+    app.update();
+    app
+}
+
+fn shot(height: f32, angle: f32) -> Shot {
+    Shot {
+        height,
+        angle,
+        speed: 600.,
+        delay: 0.25,
+    }
+}
+
+fn ball(app: &mut App) -> (Vec2, Vec2, f32) {
+    app.world_mut()
+        .query_filtered::<(&Position, &Velocity, &Speed), With<Ball>>()
+        .single(app.world())
+        .map(|(position, velocity, speed)| (position.coords, velocity.direction, speed.0))
+        .unwrap()
+}
+
+fn state(app: &App) -> MatchState {
+    *app.world().resource::<State<MatchState>>().get()
+}
+
+/// Runs until the drill is over, returning the score.
+fn finish(app: &mut App) -> [usize; 4] {
+    for _ in 0..64 * 30 {
+        app.update();
+        if *app.world().resource::<State<AppState>>().get() == AppState::GameOver {
+            return app.world().resource::<Score>().points;
+        }
+    }
+    panic!("the drill never finished");
+}
+
+#[test]
+fn launcher_fires_from_the_wall_across_the_paddle() {
+    let mut app = app(false, vec![shot(150., -30.)]);
+
+    let paddles: Vec<Edge> = app
+        .world_mut()
+        .query_filtered::<&Edge, With<Paddle>>()
+        .iter(app.world())
+        .copied()
+        .collect();
+    assert_eq!(paddles, [Edge::Right]);
+    let mut walls: Vec<Edge> = app
+        .world_mut()
+        .query::<&Gutter>()
+        .iter(app.world())
+        .map(|gutter| gutter.0)
+        .collect();
+    walls.sort_by_key(|edge| *edge as usize);
+    assert_eq!(walls, [Edge::Left, Edge::Top, Edge::Bottom]);
+
+    // The ball waits at the launcher for the shot's delay
+    let (from, direction, speed) = ball(&mut app);
+    assert!(from.x < -600.);
+    assert_eq!(from.y, 150.);
+    assert_eq!(direction, Vec2::ZERO);
+    assert_eq!(speed, 600.);
+    for _ in 0..15 {
+        app.update();
+    }
+    assert_eq!(state(&app), MatchState::Serving);
+
+    app.update();
+    app.update();
+    assert_eq!(state(&app), MatchState::Rally);
+    let (_, direction, _) = ball(&mut app);
+    let expected = Vec2::from_angle((-30f32).to_radians());
+    assert!((direction - expected).length() < 1e-5);
+}
+
+#[test]
+fn shots_past_the_paddle_are_missed() {
+    // Far from the paddle waiting in the middle of its edge
+    let mut app = app(false, vec![shot(300., 0.), shot(-300., 0.)]);
+
+    assert_eq!(finish(&mut app), [0, 2, 0, 0]);
+}
+
+#[test]
+fn returned_shots_are_caught_by_the_launcher() {
+    let mut app = app(false, vec![shot(0., 0.), shot(300., 0.)]);
+
+    // Straight at the paddle and straight back
+    for _ in 0..64 * 10 {
+        app.update();
+        if app.world().resource::<DrillProgress>().shot == 1 {
+            break;
+        }
+    }
+    assert_eq!(app.world().resource::<Score>().points, [1, 0, 0, 0]);
+    assert_eq!(ball(&mut app).0.y, 300.);
+
+    assert_eq!(finish(&mut app), [1, 1, 0, 0]);
+}
+
+#[test]
+fn wall_drills_keep_the_rally_going() {
+    let mut app = app(true, vec![shot(0., 0.)]);
+
+    // Straight back and forth off the far wall, every return counted
+    for _ in 0..64 * 10 {
+        app.update();
+    }
+    assert!(app.world().resource::<Score>().points[0] >= 2);
+    assert_eq!(app.world().resource::<DrillProgress>().shot, 0);
+    assert_eq!(state(&app), MatchState::Rally);
+}
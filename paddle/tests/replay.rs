@@ -0,0 +1,147 @@
+//! Records a headless match and plays it back at a different frame rate,
+//! checking that the replay reproduces the match exactly.
+
+mod common;
+
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::ActionState;
+use paddle::{
+    arena::Arena,
+    components::Player,
+    game::{AppState, GameRng, MatchRules, Score, Scorer},
+    input::Action,
+    replay::{Recording, Replay},
+    tuning::PaddleTuning,
+    PaddlePlugin,
+};
+
+/// Upper bound on updates per match, so a stalemate fails rather than hangs.
+const MAX_UPDATES: usize = 64 * 60 * 10;
+
+fn app(plugin: PaddlePlugin, ticks_per_update: f64) -> App {
+    common::app_updating_every(plugin, ticks_per_update * common::TICK)
+}
+
+fn is_over(app: &App) -> bool {
+    *app.world().resource::<State<AppState>>().get() == AppState::GameOver
+}
+
+fn final_score(app: &App) -> (usize, usize) {
+    let score = app.world().resource::<Score>();
+    (score.points(Scorer::PLAYER), score.points(Scorer::OPPONENT))
+}
+
+/// Plays a match against the AI with `tuning`, moving the player's paddle up
+/// and down on a fixed pattern, and returns its recording and score.
+fn record_match(tuning: PaddleTuning) -> (Replay, (usize, usize)) {
+    let mut app = app(PaddlePlugin::headless(Arena::default()), 1.);
+    app.insert_resource(GameRng::from_seed(42))
+        .insert_resource(tuning)
+        .insert_resource(MatchRules {
+            points_to_win: 3,
+            win_by_two: false,
+            best_of: 1,
+            ..Default::default()
+        });
+    app.update();
+
+    let player = app
+        .world_mut()
+        .query_filtered::<Entity, With<Player>>()
+        .single(app.world())
+        .unwrap();
+
+    for update in 0..MAX_UPDATES {
+        let mut action = app
+            .world_mut()
+            .get_mut::<ActionState<Action>>(player)
+            .unwrap();
+        action.release(&Action::MoveUp);
+        action.release(&Action::MoveDown);
+        match update / 45 % 3 {
+            0 => action.press(&Action::MoveUp),
+            1 => action.press(&Action::MoveDown),
+            _ => {}
+        }
+
+        app.update();
+        if is_over(&app) {
+            break;
+        }
+    }
+    assert!(is_over(&app), "the recorded match never finished");
+
+    let replay = app
+        .world()
+        .resource::<Recording>()
+        .replay()
+        .cloned()
+        .unwrap();
+    (replay, final_score(&app))
+}
+
+#[test]
+fn replay_reproduces_the_score() {
+    let (replay, score) = record_match(PaddleTuning::default());
+    assert!(replay.inputs.len() > 1, "the player never moved");
+
+    // Several ticks per frame, and not a whole number of them
+    let mut app = app(
+        PaddlePlugin {
+            replay: Some(replay.clone()),
+            ..PaddlePlugin::headless(Arena::default())
+        },
+        2.5,
+    );
+    for _ in 0..MAX_UPDATES {
+        app.update();
+        if is_over(&app) {
+            break;
+        }
+    }
+
+    assert!(is_over(&app), "the replayed match never finished");
+    assert_eq!(final_score(&app), score);
+    // Playing it back records the very same match again
+    assert_eq!(app.world().resource::<Recording>().replay(), Some(&replay));
+}
+
+#[test]
+fn replay_survives_a_round_trip_through_ron() {
+    let (replay, _) = record_match(PaddleTuning::default());
+
+    let text = ron::to_string(&replay).unwrap();
+    assert_eq!(Replay::parse(&text).unwrap(), replay);
+}
+
+#[test]
+fn replay_keeps_the_tuning_it_was_recorded_with() {
+    // Recorded with an edited tuning, and played back in a game that has the
+    // default one again
+    let tuning = PaddleTuning {
+        paddle_speed: 160.,
+        ball_speed: 560.,
+        ..Default::default()
+    };
+    let (replay, score) = record_match(tuning.clone());
+    assert_eq!(replay.tuning, tuning);
+
+    let mut app = app(
+        PaddlePlugin {
+            replay: Some(replay.clone()),
+            ..PaddlePlugin::headless(Arena::default())
+        },
+        1.,
+    );
+    for _ in 0..MAX_UPDATES {
+        app.update();
+        if is_over(&app) {
+            break;
+        }
+    }
+
+    assert!(is_over(&app), "the replayed match never finished");
+    assert_eq!(app.world().resource::<PaddleTuning>(), &tuning);
+    assert_eq!(final_score(&app), score);
+    assert_eq!(app.world().resource::<Recording>().replay(), Some(&replay));
+}
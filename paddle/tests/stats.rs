@@ -0,0 +1,44 @@
+//! Plays a headless match out and checks that the statistics gathered along
+//! the way add up.
+
+mod common;
+
+use bevy::prelude::*;
+use paddle::{
+    arena::Arena,
+    game::{AppState, MatchRules},
+    stats::MatchStats,
+    tuning::PaddleTuning,
+    PaddlePlugin,
+};
+
+#[test]
+fn stats_add_up_over_a_match() {
+    let mut app = common::app(PaddlePlugin::headless(Arena::default()));
+    app.insert_resource(MatchRules {
+        points_to_win: 3,
+        ..Default::default()
+    });
+
+    for _ in 0..64 * 300 {
+        app.update();
+        if *app.world().resource::<State<AppState>>().get() == AppState::GameOver {
+            break;
+        }
+    }
+    assert_eq!(
+        *app.world().resource::<State<AppState>>().get(),
+        AppState::GameOver,
+        "the match never ended"
+    );
+
+    let stats = app.world().resource::<MatchStats>();
+    assert!(stats.rallies.len() >= 3);
+    assert_eq!(stats.serves.iter().sum::<usize>(), stats.rallies.len());
+    assert_eq!(
+        stats.hits.iter().sum::<usize>(),
+        stats.rallies.iter().sum::<usize>()
+    );
+    assert_eq!(stats.longest_rally, *stats.rallies.iter().max().unwrap());
+    assert!(stats.max_ball_speed >= PaddleTuning::default().ball_speed);
+}
@@ -0,0 +1,53 @@
+//! Changes the tuning of a headless match while it is running and checks that
+//! the game picks it up.
+
+mod common;
+
+use bevy::prelude::*;
+use paddle::{
+    arena::Arena,
+    components::{Ball, Gutter, Paddle, Position, Shape},
+    tuning::PaddleTuning,
+    PaddlePlugin,
+};
+
+#[test]
+fn changed_tuning_resizes_everything_right_away() {
+    let mut app = common::app(PaddlePlugin::headless(Arena::default()));
+    app.update();
+
+    let tuning = PaddleTuning {
+        paddle_height: 80.,
+        ball_size: 8.,
+        gutter_height: 30.,
+        ..Default::default()
+    };
+    app.insert_resource(tuning.clone());
+    app.update();
+
+    for shape in app
+        .world_mut()
+        .query_filtered::<&Shape, With<Paddle>>()
+        .iter(app.world())
+    {
+        assert_eq!(shape.size, tuning.paddle_size());
+    }
+    for shape in app
+        .world_mut()
+        .query_filtered::<&Shape, With<Ball>>()
+        .iter(app.world())
+    {
+        assert_eq!(shape.size, Vec2::splat(8.));
+    }
+
+    let half_height = app.world().resource::<Arena>().half_height();
+    for (shape, position) in app
+        .world_mut()
+        .query_filtered::<(&Shape, &Position), With<Gutter>>()
+        .iter(app.world())
+    {
+        assert_eq!(shape.size.y, 30.);
+        // Still flush with the edge of the arena
+        assert_eq!(position.coords.y.abs() + 15., half_height);
+    }
+}